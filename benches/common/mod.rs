@@ -0,0 +1,12 @@
+//! Signal helpers shared by the benchmark targets.
+
+/// Deterministic white-ish noise so runs are comparable.
+pub fn noise(len: usize) -> Vec<f32> {
+    let mut state = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1u32 << 24) as f32 * 0.5 - 0.25
+        })
+        .collect()
+}
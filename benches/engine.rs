@@ -0,0 +1,43 @@
+mod common;
+
+use common::noise;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use msg_encoder::{decoder, encoder, Engine};
+
+const SAMPLE_RATE: u32 = 16_000;
+
+/// Short clips, as sent by a batch server: planning dominates a fresh call.
+fn encode_fresh_vs_engine(c: &mut Criterion) {
+    let samples = noise(SAMPLE_RATE as usize); // 1 s
+    let mut group = c.benchmark_group("encode_1s");
+
+    group.bench_function("fresh", |b| {
+        b.iter(|| encoder::encode_audio_samples(black_box(&samples), SAMPLE_RATE, "hi", 32, 15))
+    });
+
+    let mut engine = Engine::new();
+    group.bench_function("engine", |b| {
+        b.iter(|| engine.encode_audio_samples(black_box(&samples), SAMPLE_RATE, "hi", 32, 15))
+    });
+
+    group.finish();
+}
+
+fn decode_fresh_vs_engine(c: &mut Criterion) {
+    let samples = encoder::encode_audio_samples(&noise(SAMPLE_RATE as usize), SAMPLE_RATE, "hi", 32, 15);
+    let mut group = c.benchmark_group("decode_1s");
+
+    group.bench_function("fresh", |b| {
+        b.iter(|| decoder::decode_audio_samples(black_box(&samples), SAMPLE_RATE))
+    });
+
+    let mut engine = Engine::new();
+    group.bench_function("engine", |b| {
+        b.iter(|| engine.decode_audio_samples(black_box(&samples), SAMPLE_RATE))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, encode_fresh_vs_engine, decode_fresh_vs_engine);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+mod common;
+
+use common::noise;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use msg_encoder::decoder::{decide_bits, spectral_scores};
+
+// Usable bins (spectrum length - START_BIN) for FFT sizes 256..2048
+const BIN_COUNTS: [usize; 4] = [81, 209, 465, 977];
+
+fn bench_spectral_scores(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spectral_scores");
+    for &bins in BIN_COUNTS.iter() {
+        let magnitudes: Vec<f32> = noise(bins).iter().map(|x| x.abs() + 1e-3).collect();
+        group.bench_with_input(BenchmarkId::from_parameter(bins), &magnitudes, |b, mags| {
+            b.iter(|| spectral_scores(black_box(mags), 3))
+        });
+    }
+    group.finish();
+}
+
+fn bench_decide_bits(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decide_bits");
+    for &bins in BIN_COUNTS.iter() {
+        let scores: Vec<f32> = noise(bins);
+        let votes: Vec<f32> = scores.iter().map(|s| (s + 0.25) * 2.0).collect();
+        group.bench_with_input(BenchmarkId::from_parameter(bins), &(scores, votes), |b, (scores, votes)| {
+            b.iter(|| decide_bits(black_box(scores), black_box(votes), 0.0, 0.2, -0.2, false))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_spectral_scores, bench_decide_bits);
+criterion_main!(benches);
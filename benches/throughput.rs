@@ -0,0 +1,109 @@
+mod common;
+
+use common::noise;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use msg_encoder::encoder::{FRAME_DURATIONS_MS, SAMPLE_RATES};
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions, Engine};
+
+// Signal lengths in seconds
+const DURATIONS_S: [u32; 3] = [1, 10, 60];
+
+const MESSAGE: &str = "hi";
+const STRENGTH_PERCENT: u32 = 15;
+
+// Throughput is counted in milliseconds of audio, so `1 Kelem/s` reads as 1x realtime.
+fn audio_ms(seconds: u32) -> Throughput {
+    Throughput::Elements(u64::from(seconds) * 1000)
+}
+
+fn encode_throughput(c: &mut Criterion) {
+    let mut engine = Engine::new();
+    let mut group = c.benchmark_group("encode_realtime");
+    group.sample_size(10);
+
+    for &rate in SAMPLE_RATES.iter() {
+        for &seconds in DURATIONS_S.iter() {
+            let samples = noise((rate * seconds) as usize);
+            group.throughput(audio_ms(seconds));
+            for &frame_ms in FRAME_DURATIONS_MS.iter() {
+                let id = BenchmarkId::new(format!("{rate}Hz_{frame_ms}ms"), format!("{seconds}s"));
+                group.bench_with_input(id, &samples, |b, samples| {
+                    b.iter(|| {
+                        engine.encode_audio_samples(
+                            black_box(samples),
+                            rate,
+                            MESSAGE,
+                            frame_ms,
+                            STRENGTH_PERCENT,
+                        )
+                    })
+                });
+            }
+        }
+    }
+
+    group.finish();
+}
+
+// The decoder always analyses 32 ms frames, so only the sample rate and length vary its cost.
+fn decode_throughput(c: &mut Criterion) {
+    let mut engine = Engine::new();
+    let mut group = c.benchmark_group("decode_realtime");
+    group.sample_size(10);
+
+    for &rate in SAMPLE_RATES.iter() {
+        for &seconds in DURATIONS_S.iter() {
+            let samples =
+                engine.encode_audio_samples(&noise((rate * seconds) as usize), rate, MESSAGE, 32, STRENGTH_PERCENT);
+            group.throughput(audio_ms(seconds));
+            let id = BenchmarkId::new(format!("{rate}Hz"), format!("{seconds}s"));
+            group.bench_with_input(id, &samples, |b, samples| {
+                b.iter(|| engine.decode_audio_samples(black_box(samples), rate))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// Embedding modes head to head on the same material (16 kHz, 32 ms frames, 10 s).
+fn mode_comparison(c: &mut Criterion) {
+    const RATE: u32 = 16_000;
+    const SECONDS: u32 = 10;
+
+    let mut engine = Engine::new();
+    let audio = noise((RATE * SECONDS) as usize);
+    let mut group = c.benchmark_group("mode_comparison");
+    group.sample_size(10);
+    group.throughput(audio_ms(SECONDS));
+
+    for mode in [
+        EmbeddingMode::Multiplicative,
+        EmbeddingMode::Additive,
+        EmbeddingMode::Qim,
+        EmbeddingMode::Phase,
+        EmbeddingMode::Echo,
+    ] {
+        let encode = EncodeOptions {
+            mode,
+            ..EncodeOptions::default()
+        };
+        let decode = DecodeOptions {
+            mode: Some(mode),
+            ..DecodeOptions::default()
+        };
+        let (encoded, _) = engine.encode_with_options(&audio, RATE, MESSAGE, &encode).unwrap();
+
+        group.bench_function(BenchmarkId::new("encode", format!("{mode:?}")), |b| {
+            b.iter(|| engine.encode_with_options(black_box(&audio), RATE, MESSAGE, &encode).unwrap())
+        });
+        group.bench_function(BenchmarkId::new("decode", format!("{mode:?}")), |b| {
+            b.iter(|| engine.decode_with_options(black_box(&encoded), RATE, &decode))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, encode_throughput, decode_throughput, mode_comparison);
+criterion_main!(benches);
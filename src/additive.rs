@@ -0,0 +1,90 @@
+use realfft::num_complex::Complex;
+
+use crate::energy::{self, db_to_amplitude};
+use crate::scheme::{self, AnalysisFrame, EmbedFrame, WatermarkScheme, START_BIN};
+
+// =============================================================================
+// Additive embedding - inject a carrier instead of scaling existing energy
+// =============================================================================
+
+// Masking model: a bin of magnitude |X| hides anything MASK_OFFSET_DB below it,
+// and that allowance falls off by MASK_SLOPE_DB per bin on either side.
+const MASK_OFFSET_DB: f32 = 12.0;
+const MASK_SLOPE_DB: f32 = 3.0;
+
+// Carrier level allowed where there is nothing to mask it (empty bands, silence),
+// expressed as the level of a single sinusoid in dBFS.
+const CARRIER_FLOOR_DB: f32 = -60.0;
+
+/// Per-bin masking threshold (same units as the FFT magnitudes) written into `mask`.
+///
+/// Computed with a forward and a backward max-decay pass in the dB domain, which
+/// is a linear-time triangular spreading function.
+pub fn masking_threshold(spectrum: &[Complex<f32>], mask: &mut Vec<f32>) {
+    let floor_db = amplitude_to_db(scheme::sinusoid_magnitude(CARRIER_FLOOR_DB, spectrum.len()));
+
+    mask.clear();
+    mask.extend(spectrum.iter().map(|c| amplitude_to_db(c.norm()) - MASK_OFFSET_DB));
+
+    for idx in 1..mask.len() {
+        mask[idx] = mask[idx].max(mask[idx - 1] - MASK_SLOPE_DB); // spread upwards
+    }
+    for idx in (0..mask.len().saturating_sub(1)).rev() {
+        mask[idx] = mask[idx].max(mask[idx + 1] - MASK_SLOPE_DB); // spread downwards
+    }
+
+    for value in mask.iter_mut() {
+        *value = db_to_amplitude(value.max(floor_db));
+    }
+}
+
+/// Add a random-phase carrier of `strength × mask` to every "1" bin from `start_bin`.
+/// "0" bins are left alone, so the decoder sees 1s as peaks above their neighbours.
+pub fn embed_additive(
+    spectrum: &mut [Complex<f32>],
+    mask: &[f32],
+    bits: &[u8],
+    start_bin: usize,
+    strength: f32,
+    frame_index: usize,
+) {
+    for (offset, (&bit, bin)) in bits.iter().zip(&mut spectrum[start_bin..]).enumerate() {
+        if bit == 1 {
+            let idx = start_bin + offset;
+            let phase = energy::carrier_phase(frame_index, idx);
+            *bin += Complex::from_polar(strength * mask[idx], phase);
+        }
+    }
+    scheme::real_nyquist(spectrum);
+}
+
+/// Masked additive carrier; decoded with the same spectral contrast as multiplicative.
+pub struct AdditiveScheme;
+
+impl WatermarkScheme for AdditiveScheme {
+    fn id(&self) -> &'static str {
+        "additive"
+    }
+
+    fn capacity(&self, spectrum_len: usize) -> usize {
+        spectrum_len.saturating_sub(START_BIN)
+    }
+
+    fn reads_single_frames(&self) -> bool {
+        false
+    }
+
+    fn embed_frame(&self, frame: &mut EmbedFrame<'_>) {
+        // Carrier sized from the frame's own masking threshold
+        masking_threshold(frame.spectrum, frame.scratch);
+        embed_additive(frame.spectrum, frame.scratch, frame.bits, START_BIN, frame.strength, frame.index);
+    }
+
+    fn analyse_frame(&self, frame: &mut AnalysisFrame<'_>, scores: &mut Vec<f32>) {
+        scheme::spectral_contrast(frame, scores);
+    }
+}
+
+fn amplitude_to_db(amplitude: f32) -> f32 {
+    20.0 * amplitude.max(1e-12).log10()
+}
@@ -0,0 +1,41 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// =============================================================================
+// Authenticated payloads - truncated HMAC-SHA256 tags under a shared secret
+// =============================================================================
+//
+// The bitstream layout is public, so anyone can embed a well-formed mark.
+// With a key, the encoder appends the first `TAG_BYTES` of
+// HMAC-SHA256(key, payload) to the payload and sets `FLAG_AUTHENTICATED` in
+// the header (whose length then counts the tag). A decoder holding the same
+// key recomputes the tag: a match means the mark was made by a key holder,
+// while a forger without the key has one chance in 2^32 per attempt.
+//
+// The tag is embedded like the rest of the payload, so a genuine mark read
+// with bit errors also fails to authenticate; the check is only as good as
+// the channel.
+
+/// Bytes of the HMAC-SHA256 output kept as the tag.
+pub const TAG_BYTES: usize = 4;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn mac(key: &[u8], payload: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    mac
+}
+
+/// Truncated tag for `payload` under `key`.
+pub fn tag(key: &[u8], payload: &[u8]) -> [u8; TAG_BYTES] {
+    let digest = mac(key, payload).finalize().into_bytes();
+    let mut tag = [0; TAG_BYTES];
+    tag.copy_from_slice(&digest[..TAG_BYTES]);
+    tag
+}
+
+/// Whether `tag` is the truncated tag of `payload` under `key` (compared in constant time).
+pub fn verify(key: &[u8], payload: &[u8], tag: &[u8]) -> bool {
+    tag.len() == TAG_BYTES && mac(key, payload).verify_truncated_left(tag).is_ok()
+}
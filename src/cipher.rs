@@ -0,0 +1,101 @@
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::header::Header;
+
+// =============================================================================
+// Encrypted payloads - ChaCha20-Poly1305 under a shared secret
+// =============================================================================
+//
+// Anyone can read a payload off the public layout, so payloads that must stay
+// private (customer IDs and the like) can be sealed with ChaCha20-Poly1305.
+// The header stays in the clear (the decoder needs it to find the payload) and
+// is bound to the ciphertext as associated data; `FLAG_ENCRYPTED` marks the
+// payload as sealed and its length counts the IV and the 16-byte Poly1305 tag.
+//
+// A mark has little room, so rather than a random 12-byte nonce each payload
+// carries a synthetic IV (SIV): the first `IV_BYTES` of HMAC-SHA256 over the
+// header and the plaintext, sent ahead of the ciphertext. The cipher key is
+// derived from the secret and the header, but the nonce deliberately is not
+// derived from the header alone: a header holds little more than the length,
+// so two messages of the same length would share a key and nonce, reusing the
+// keystream (their XOR leaks) and the Poly1305 key (tags can be forged).
+// With the SIV, distinct messages get distinct nonces under one secret, and
+// `open` recomputes the IV from the decrypted plaintext, so a forged IV fails
+// as a forged tag does.
+//
+// The trade-off: encryption is deterministic. The same message sealed under
+// the same secret and header always gives the same payload, so anyone
+// comparing marks can tell that two recordings carry the same message (though
+// not what it is). Callers who must hide repeats should make each message
+// unique, e.g. by including a counter or timestamp.
+//
+// The tag alone is more than one frame holds after the pilot and header, so
+// encrypted payloads need the segmented layout (and with it QIM or phase).
+
+/// Bytes of the synthetic IV that leads a sealed payload.
+pub const IV_BYTES: usize = 8;
+
+/// Bytes sealing adds to a payload (the synthetic IV and the Poly1305 tag).
+pub const SEAL_BYTES: usize = IV_BYTES + 16;
+
+/// Encrypt `plaintext` for a stream with `header` (whose length must already count [`SEAL_BYTES`]).
+pub fn seal(secret: &[u8], header: &Header, plaintext: &[u8]) -> Vec<u8> {
+    let aad = header_bytes(header);
+    let iv = iv_mac(secret, &aad, plaintext).finalize().into_bytes();
+    let iv = &iv[..IV_BYTES];
+    let payload = Payload { msg: plaintext, aad: &aad };
+    let sealed = cipher(secret, &aad)
+        .encrypt(&nonce(iv), payload)
+        .expect("ChaCha20-Poly1305 seals any payload that fits a header");
+    [iv, &sealed].concat()
+}
+
+/// Decrypt a sealed payload; `None` if the secret is wrong or the ciphertext, IV or header was damaged.
+pub fn open(secret: &[u8], header: &Header, sealed: &[u8]) -> Option<Vec<u8>> {
+    let aad = header_bytes(header);
+    let (iv, ciphertext) = sealed.split_at_checked(IV_BYTES)?;
+    let plaintext = cipher(secret, &aad).decrypt(&nonce(iv), Payload { msg: ciphertext, aad: &aad }).ok()?;
+    iv_mac(secret, &aad, &plaintext).verify_truncated_left(iv).ok()?; // the IV must be the plaintext's
+    Some(plaintext)
+}
+
+/// The packed header bits, bound to the ciphertext as associated data.
+fn header_bytes(header: &Header) -> Vec<u8> {
+    header
+        .to_bits()
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |acc, &bit| (acc << 1) | bit))
+        .collect()
+}
+
+/// Cipher keyed by HMAC-SHA256(secret, label || header).
+fn cipher(secret: &[u8], header: &[u8]) -> ChaCha20Poly1305 {
+    let mut mac = hmac(secret);
+    mac.update(b"msg_encoder payload key");
+    mac.update(header);
+    let key: [u8; 32] = mac.finalize().into_bytes().into();
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+/// HMAC-SHA256(secret, label || header || plaintext), truncated to the synthetic IV.
+fn iv_mac(secret: &[u8], header: &[u8], plaintext: &[u8]) -> Hmac<Sha256> {
+    let mut mac = hmac(secret);
+    mac.update(b"msg_encoder payload iv");
+    mac.update(header);
+    mac.update(plaintext);
+    mac
+}
+
+/// The 12-byte nonce for an IV (zero-padded).
+fn nonce(iv: &[u8]) -> Nonce {
+    let mut nonce = Nonce::default();
+    nonce[..IV_BYTES].copy_from_slice(iv);
+    nonce
+}
+
+fn hmac(secret: &[u8]) -> Hmac<Sha256> {
+    <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC accepts keys of any length")
+}
@@ -1,20 +1,44 @@
 use std::cmp::Ordering; // for median selection
+use std::ops::Range; // probe frames in a shared score buffer
 use std::path::{Path, PathBuf}; // build file paths
 
 use hound::WavReader; // read WAV data
-use realfft::RealFftPlanner; // perform FFTs
+
+use crate::auth; // payload authentication tags
+use crate::cipher; // payload encryption
+use crate::encoder::{self, EmbeddingMode, PayloadLayout, DEFAULT_STRENGTH_PERCENT}; // built-in scheme selection and frame layout
+use crate::energy::EnergyGate; // quiet-frame gate shared with the encoder
+use crate::engine::{Engine, FftContext, FftPlans, FrameBuffers}; // cached FFT plans and frame buffers
+use crate::header::{frame_code, scheme_code, Header, FRAME_CODES_MS, HEADER_BITS}; // self-describing stream header
+use crate::fec::{self, Fec}; // payload error correction
+use crate::interleave::{self, Interleaver}; // bit-to-bin permutation
+use crate::packing; // packed text payloads
+use crate::pilot::{self, Pilot}; // sync sequence and frame detection
+use crate::scheme::{AnalysisFrame, WatermarkScheme}; // per-frame scoring
+use crate::segment::{self, SegmentField, SEGMENT_FIELD_BITS}; // stream split over frames
+use crate::threshold; // frequency-dependent decision thresholds
 
 // --- Decoder configuration mirroring the encoder ---
-const PILOT_PATTERN: [u8; 8] = [0, 1, 0, 1, 0, 1, 0, 1]; // known pilot
-const LENGTH_HEADER_BITS: usize = 16; // payload length field
-const WATERMARK_FRAME_DURATION: f32 = 0.032; // frame duration (32ms)
+pub use crate::pilot::PILOT_PATTERN; // known pilot
+pub use crate::scheme::START_BIN; // first watermark bin
+pub const LLR_LIMIT: f32 = 30.0; // |LLR| cap, so one clean bin cannot outvote a whole code path
+const LLR_MIN_SPREAD: f32 = 0.25; // class spread floor, as a fraction of the class separation (8 pilot bits vouch for little more)
+pub const LENGTH_HEADER_BITS: usize = 16; // payload length field
+pub const DEFAULT_FRAME_DURATION_MS: u32 = 32; // frame duration when neither the options nor a header name one
 const SAMPLE_DIVISOR: f32 = 32768.0; // i16 -> f32 scale
-const START_BIN: usize = 48; // first watermark bin
+pub const MAX_CYCLE_MESSAGE_BYTES: usize = 64; // longest message searched for in cycling schemes
+const ALIGN_PROBE_FRAMES: usize = 16; // frames scored per candidate offset in the alignment search
+const HEADER_PROBE_FRAMES: usize = 512; // frames read from the start of a recording to find its header (16 s at 32 ms)
+const MIN_SEGMENT_READS: usize = 3; // segment fields that must agree before a probe calls the layout segmented
 
 /// Struct returned by the decoder.
 pub struct DecodedWatermark {
     pub message: String,    // recovered UTF-8 text
     pub raw_bytes: Vec<u8>, // raw byte payload
+    pub header: Option<Header>, // version 1 header, when the stream has a valid one
+    pub segments_seen: Vec<usize>, // segmented layout: indices read from at least one frame (if some are missing, the message is empty)
+    pub authenticated: Option<bool>, // with `DecodeOptions::auth_key`: whether the payload's tag checks out
+    pub decrypted: Option<bool>, // encrypted payloads: whether `DecodeOptions::encryption_key` opened them
 }
 
 /// Visualization data for decoding
@@ -27,9 +51,58 @@ pub struct DecodeVisualization {
     pub avg_high: f32,
     pub avg_low: f32,
     pub inverted: bool,
+    pub llrs: Vec<f32>, // per-position log-likelihood ratio, ln P(1)/P(0), from the pilot's score spread
     pub first_frame: Vec<f32>,
 }
 
+impl DecodedWatermark {
+    /// No message: what the decoder returns when it cannot read one.
+    fn empty() -> Self {
+        Self {
+            message: String::new(),
+            raw_bytes: Vec::new(),
+            header: None,
+            segments_seen: Vec::new(),
+            authenticated: None,
+            decrypted: None,
+        }
+    }
+}
+
+impl DecodeVisualization {
+    /// Nothing decoded beyond the first frame's samples.
+    fn empty(first_frame: Vec<f32>) -> Self {
+        Self {
+            bit_sequence: Vec::new(),
+            scores: Vec::new(),
+            votes: Vec::new(),
+            threshold: 0.0,
+            avg_high: 0.0,
+            avg_low: 0.0,
+            inverted: false,
+            llrs: Vec::new(),
+            first_frame,
+        }
+    }
+}
+
+/// Decoder settings
+#[derive(Clone, Debug, Default)]
+pub struct DecodeOptions {
+    pub mode: Option<EmbeddingMode>, // built-in scheme (None = the one the header names)
+    pub layout: Option<PayloadLayout>, // None = as the header says
+    pub frame_duration_ms: Option<u32>, // the encoder's frames (None = as the header says)
+    pub interleaver: Interleaver, // must match the encoder
+    pub pilot: Pilot, // must match the encoder
+    pub strength_percent: Option<u32>, // QIM: the encoder's strength, which sets the lattice step (None = the default)
+    pub false_alarm: Option<f64>, // chance an unmarked frame, or recording, passes the pilot check (None = `pilot::DEFAULT_FALSE_ALARM`)
+    pub adaptive_threshold: bool, // decide bits against a threshold curve fitted across the band, not the pilot's alone
+    pub search_alignment: bool, // cropped excerpt: find the encoder's frame grid first (slower)
+    pub energy_gate: Option<EnergyGate>, // ignore frames below the gate (use the encoder's gate)
+    pub encryption_key: Option<Vec<u8>>, // shared secret for opening encrypted payloads
+    pub auth_key: Option<Vec<u8>>, // shared secret for checking authenticated payloads
+}
+
 /// WASM-compatible decoder that accepts audio samples directly
 pub fn decode_audio_samples(samples: &[f32], sample_rate: u32) -> DecodedWatermark {
     let (decoded, _) = decode_audio_samples_with_viz(samples, sample_rate);
@@ -38,46 +111,355 @@ pub fn decode_audio_samples(samples: &[f32], sample_rate: u32) -> DecodedWaterma
 
 /// WASM-compatible decoder that returns both decoded watermark and visualization data
 pub fn decode_audio_samples_with_viz(samples: &[f32], sample_rate: u32) -> (DecodedWatermark, DecodeVisualization) {
+    decode_audio_samples_with_options(samples, sample_rate, &DecodeOptions::default())
+}
+
+/// Decoder taking the full set of [`DecodeOptions`]
+pub fn decode_audio_samples_with_options(
+    samples: &[f32],
+    sample_rate: u32,
+    options: &DecodeOptions,
+) -> (DecodedWatermark, DecodeVisualization) {
+    let mut engine = Engine::new();
+    engine.decode_with_options(samples, sample_rate, options)
+}
+
+/// Decoder generic over the [`WatermarkScheme`] (`options.mode` is ignored)
+pub fn decode_audio_samples_with_scheme<S: WatermarkScheme + ?Sized>(
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    options: &DecodeOptions,
+) -> (DecodedWatermark, DecodeVisualization) {
+    let mut engine = Engine::new();
+    decode_with_engine(&mut engine, scheme, samples, sample_rate, options)
+}
+
+/// Decoder body shared by the free functions and [`Engine`]
+pub(crate) fn decode_with_engine<S: WatermarkScheme + ?Sized>(
+    engine: &mut Engine,
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    options: &DecodeOptions,
+) -> (DecodedWatermark, DecodeVisualization) {
+    let options = configure_with_scheme(engine, scheme, samples, sample_rate, options);
+    let (mut decoded, viz) = read_watermark(engine, scheme, samples, sample_rate, &options);
+    open_payload(&mut decoded, &options);
+    (decoded, viz)
+}
+
+impl DecodeOptions {
+    /// Samples per frame at `sample_rate` (the default duration until configured from a header).
+    pub fn frame_len(&self, sample_rate: u32) -> usize {
+        encoder::frame_length_samples(sample_rate, self.frame_duration_ms.unwrap_or(DEFAULT_FRAME_DURATION_MS))
+    }
+
+    /// The built-in scheme to read with (the default until configured from a header).
+    pub(crate) fn scheme(&self) -> &'static dyn WatermarkScheme {
+        self.mode.unwrap_or_default().scheme()
+    }
+
+    /// Whether frames are read as segments of one stream.
+    pub(crate) fn is_segmented(&self) -> bool {
+        self.layout == Some(PayloadLayout::Segmented)
+    }
+}
+
+// --- Configuration from the header -------------------------------------------
+//
+// The header names the scheme and frame duration, and flags the segmented
+// layout, but it sits inside the watermark: reading it takes the scheme and
+// frame length it describes. `configure` therefore tries each candidate the
+// options leave open (built-in schemes in `EmbeddingMode::ALL` order, coded
+// frame durations with the default first) on the first `HEADER_PROBE_FRAMES`
+// frames, and keeps the first whose frames carry a header naming it, or
+// segment fields that agree. Anything set in the options is used as is; what
+// no probe settles falls back to the defaults (legacy streams have no header).
+
+/// `options` with the scheme, frame duration and layout it leaves open read from the recording's header.
+pub fn configure(engine: &mut Engine, samples: &[f32], sample_rate: u32, options: &DecodeOptions) -> DecodeOptions {
+    if options.mode.is_none() {
+        for mode in EmbeddingMode::ALL {
+            let trial = DecodeOptions { mode: Some(mode), ..options.clone() };
+            if let Some(configured) = probe(engine, mode.scheme(), samples, sample_rate, &trial) {
+                return configured;
+            }
+        }
+    }
+    let options = DecodeOptions { mode: Some(options.mode.unwrap_or_default()), ..options.clone() };
+    configure_with_scheme(engine, options.scheme(), samples, sample_rate, &options)
+}
+
+/// [`configure`] for a given scheme: the frame duration and layout `options` leave open.
+pub fn configure_with_scheme<S: WatermarkScheme + ?Sized>(
+    engine: &mut Engine,
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    options: &DecodeOptions,
+) -> DecodeOptions {
+    if options.frame_duration_ms.is_some() && options.layout.is_some() {
+        return options.clone(); // nothing to read
+    }
+    probe(engine, scheme, samples, sample_rate, options).unwrap_or_else(|| DecodeOptions {
+        frame_duration_ms: Some(options.frame_duration_ms.unwrap_or(DEFAULT_FRAME_DURATION_MS)),
+        layout: Some(options.layout.unwrap_or_default()),
+        ..options.clone()
+    })
+}
+
+/// `options` completed from the first frame duration at which `scheme` finds a header (or segments).
+fn probe<S: WatermarkScheme + ?Sized>(
+    engine: &mut Engine,
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    options: &DecodeOptions,
+) -> Option<DecodeOptions> {
+    let durations = match options.frame_duration_ms {
+        Some(ms) => vec![ms],
+        None => std::iter::once(DEFAULT_FRAME_DURATION_MS)
+            .chain(FRAME_CODES_MS.into_iter().filter(|&ms| ms != DEFAULT_FRAME_DURATION_MS))
+            .collect(),
+    };
+    durations.into_iter().find_map(|ms| {
+        let trial = DecodeOptions { frame_duration_ms: Some(ms), ..options.clone() };
+        let frame_len = trial.frame_len(sample_rate);
+        let ctx = engine.context(frame_len.next_power_of_two().max(2));
+        let mut source = FrameSource::new(scheme, samples, frame_len, sample_rate, &trial).ok()?;
+        if options.search_alignment {
+            let (offset, first_index) = frame_alignment(ctx, &source);
+            source.samples = &samples[offset..];
+            source.first_index = first_index;
+        }
+        let (header, segmented) = read_setup(ctx, &source)?;
+        let names_this = |header: &Header| header.scheme == scheme_code(scheme.id()) && header.frame_code == frame_code(ms);
+        if header.is_some_and(|header| !names_this(&header)) {
+            return None; // read, but written for another scheme or frame length
+        }
+        let layout = if segmented { PayloadLayout::Segmented } else { PayloadLayout::Repeated };
+        Some(DecodeOptions { layout: Some(options.layout.unwrap_or(layout)), ..trial })
+    })
+}
+
+/// The header `source`'s first frames carry and whether they are segments, if they carry either.
+fn read_setup<S: WatermarkScheme + ?Sized>(ctx: &mut FftContext, source: &FrameSource<S>) -> Option<(Option<Header>, bool)> {
+    let pilot = &source.pilot;
+    let capacity = source.scheme.capacity(ctx.buffers.spectrum.len());
+    if capacity < pilot.len() + LENGTH_HEADER_BITS {
+        let fold = fold_cycle(&cycle_scores(ctx, source), pilot)?; // few bits per frame: the header is in the cycle
+        return fold.header.map(|header| (Some(header), false));
+    }
+
+    // Scores and hard bits after the pilot of the first frames that vote, each kept in one buffer for
+    // every probe frame: `frames` holds their ranges
+    let stride = ctx.buffers.spectrum.len(); // a scheme scores at most one position per bin
+    let mut probed = Vec::with_capacity(HEADER_PROBE_FRAMES * stride);
+    let mut probed_bits = Vec::with_capacity(HEADER_PROBE_FRAMES * stride);
+    let mut frames: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    for (index, frame) in source.samples.chunks(source.frame_len).take(HEADER_PROBE_FRAMES).enumerate() {
+        let Some(correlation) = frame_correlation(source, &ctx.plans, &mut ctx.buffers, index, frame) else {
+            continue;
+        };
+        let scores = &ctx.buffers.scores;
+        if let Some((threshold, inverted)) = frame_verdict(scores, pilot, correlation, source.vote) {
+            let (score_start, bit_start) = (probed.len(), probed_bits.len());
+            probed.extend_from_slice(scores);
+            hard_bits_into(&scores[pilot.len()..], threshold, inverted, &mut probed_bits);
+            frames.push((score_start..probed.len(), bit_start..probed_bits.len()));
+        }
+    }
+
+    // Repeated: every frame carries the header, so read it off their median scores. A clean read settles it;
+    // segments would leave the median noise.
+    let mut column = Vec::with_capacity(frames.len());
+    let len = frames.iter().map(|(range, _)| range.len()).min()?;
+    let scores: Vec<f32> = (0..len)
+        .map(|position| {
+            column.clear();
+            column.extend(frames.iter().map(|(range, _)| probed[range.start + position]));
+            median(&mut column).unwrap_or(0.0)
+        })
+        .collect();
+    let (avg_high, avg_low, threshold) = pilot_stats_with_pilot(&scores, pilot);
+    let repeated = hard_bits(&scores[pilot.len()..], threshold, avg_high < avg_low);
+    if let Some(header) = Header::from_bits_exact(&repeated).filter(|header| !header.is_segmented()) {
+        return Some((Some(header), false));
+    }
+
+    // Segments: fields agreeing on the cycle, and the header if segment 0 was among the frames. A repeated
+    // header can read as the same field in every frame, so the reads must step through the cycle (two
+    // indices read twice each), or segment 0 must carry a header flagged as segmented.
+    if segment::chunk_bits_with_pilot(capacity, pilot.len()).is_some() {
+        let bits = |range: &Range<usize>| &probed_bits[range.clone()];
+        let fields: Vec<SegmentField> = frames.iter().filter_map(|(_, range)| SegmentField::from_bits(bits(range))).collect();
+        if let Some(count) = majority(fields.iter().map(|field| field.count)) {
+            let agreeing: Vec<&SegmentField> = fields.iter().filter(|field| field.count == count).collect();
+            let header = frames
+                .iter()
+                .filter(|(_, range)| SegmentField::from_bits(bits(range)).is_some_and(|field| field == SegmentField { index: 0, count }))
+                .find_map(|(_, range)| Header::from_bits(&bits(range)[SEGMENT_FIELD_BITS..]));
+            let mut reads_per_index = std::collections::BTreeMap::new();
+            for field in &agreeing {
+                *reads_per_index.entry(field.index).or_insert(0usize) += 1;
+            }
+            let stepping = reads_per_index.values().filter(|&&reads| reads >= 2).count() >= 2; // not one misread
+
+            if agreeing.len() >= MIN_SEGMENT_READS && (stepping || header.is_some_and(|header| header.is_segmented())) {
+                return Some((header, true));
+            }
+        }
+    }
+
+    let header = Header::from_bits(&repeated)?;
+    Some((Some(header), header.is_segmented()))
+}
+
+/// Check, decrypt and unpack a payload read off the stream, as its header and `options` ask.
+pub(crate) fn open_payload(decoded: &mut DecodedWatermark, options: &DecodeOptions) {
+    authenticate(decoded, options.auth_key.as_deref()); // the tag covers the payload as embedded
+    decrypt(decoded, options.encryption_key.as_deref());
+    unpack(decoded);
+}
+
+/// Split an authentication tag off the payload and check it against `key`.
+/// Without a key the tag is still removed; with one, unsigned payloads report `Some(false)`.
+fn authenticate(decoded: &mut DecodedWatermark, key: Option<&[u8]>) {
+    let signed = decoded.header.is_some_and(|header| header.is_authenticated());
+    if !signed || decoded.raw_bytes.len() < auth::TAG_BYTES {
+        decoded.authenticated = key.map(|_| false);
+        return;
+    }
+
+    let tag = decoded.raw_bytes.split_off(decoded.raw_bytes.len() - auth::TAG_BYTES);
+    decoded.message = String::from_utf8_lossy(&decoded.raw_bytes).into_owned();
+    decoded.authenticated = key.map(|key| auth::verify(key, &decoded.raw_bytes, &tag));
+}
+
+/// Open an encrypted payload with `key`; the message stays empty unless it opens.
+fn decrypt(decoded: &mut DecodedWatermark, key: Option<&[u8]>) {
+    let Some(header) = decoded.header.filter(|header| header.is_encrypted()) else {
+        return; // plaintext payload
+    };
+    let plaintext = key.and_then(|key| cipher::open(key, &header, &decoded.raw_bytes));
+    decoded.decrypted = Some(plaintext.is_some());
+    match plaintext {
+        Some(plaintext) => {
+            decoded.message = String::from_utf8_lossy(&plaintext).into_owned();
+            decoded.raw_bytes = plaintext;
+        }
+        None => decoded.message.clear(), // keep the ciphertext in `raw_bytes`
+    }
+}
+
+/// Unpack a packed message (once any encryption is undone); the message stays empty if it does not unpack.
+fn unpack(decoded: &mut DecodedWatermark) {
+    let packed = decoded.header.is_some_and(|header| header.is_packed());
+    if !packed || decoded.decrypted == Some(false) {
+        return; // plain, or still encrypted
+    }
+    match packing::unpack(&decoded.raw_bytes) {
+        Some(message) => {
+            decoded.message = String::from_utf8_lossy(&message).into_owned();
+            decoded.raw_bytes = message;
+        }
+        None => decoded.message.clear(), // keep the packed bytes in `raw_bytes`
+    }
+}
+
+/// Pilot, header and payload of whichever layout `options` and the scheme call for
+fn read_watermark<S: WatermarkScheme + ?Sized>(
+    engine: &mut Engine,
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    options: &DecodeOptions,
+) -> (DecodedWatermark, DecodeVisualization) {
     // Extract first frame for visualization
-    let frame_len = ((sample_rate as f32 * WATERMARK_FRAME_DURATION)
-        .round()
-        .max(1.0)) as usize;
+    let frame_len = options.frame_len(sample_rate);
     let first_frame: Vec<f32> = samples.iter().take(frame_len).copied().collect();
 
-    let (scores, votes, _valid, _skipped, frames_inverted) =
-        summarise_frames(samples, sample_rate, 3); // aggregate frame stats
+    let fft_len = frame_len.next_power_of_two().max(2); // FFT size
+    let ctx = engine.context(fft_len);
+    let Ok(mut source) = FrameSource::new(scheme, samples, frame_len, sample_rate, options) else {
+        return (DecodedWatermark::empty(), DecodeVisualization::empty(first_frame)); // no usable pilot to look for
+    };
+    if options.search_alignment {
+        let (offset, first_index) = frame_alignment(ctx, &source);
+        source.samples = &samples[offset..]; // skip the partial frame a crop starts in
+        source.first_index = first_index;
+    }
+    let capacity = scheme.capacity(ctx.buffers.spectrum.len()); // bits per frame
+    if capacity < source.pilot.len() + LENGTH_HEADER_BITS {
+        return decode_cycle(ctx, &source, first_frame); // bitstream spread over many frames
+    }
+    if let (true, Some(chunk_bits)) = (options.is_segmented(), segment::chunk_bits_with_pilot(capacity, source.pilot.len())) {
+        if let Some(decoded) = decode_segments(ctx, &source, chunk_bits, first_frame.clone()) {
+            return decoded; // header and payload split over frames
+        }
+        // No segment fields: a message too long to segment went out whole
+    }
+    let Some(FrameSummary { scores, votes, inverted: frames_inverted, detected }) = summarise_frames(ctx, &source) else {
+        return (DecodedWatermark::empty(), DecodeVisualization::empty(first_frame)); // nothing looks marked
+    }; // aggregate frame stats, levelled if adaptive
 
-    if scores.len() < PILOT_PATTERN.len() + LENGTH_HEADER_BITS {
+    if scores.len() < source.pilot.len() + LENGTH_HEADER_BITS {
         // Return empty result if not enough bins
-        let empty_viz = DecodeVisualization {
-            bit_sequence: Vec::new(),
-            scores: Vec::new(),
-            votes: Vec::new(),
-            threshold: 0.0,
-            avg_high: 0.0,
-            avg_low: 0.0,
-            inverted: false,
-            first_frame,
-        };
-        return (DecodedWatermark {
-            message: String::new(),
-            raw_bytes: Vec::new(),
-        }, empty_viz);
+        return (DecodedWatermark::empty(), DecodeVisualization::empty(first_frame));
     }
 
-    let (avg_high, avg_low, threshold) = pilot_stats(&scores); // global threshold from pilot
+    let pilot = &source.pilot;
+    let (avg_high, avg_low, threshold) = pilot_stats_with_pilot(&scores, pilot); // global threshold from pilot
     let inverted = frames_inverted || avg_high < avg_low; // detect polarity flip (some audio can invert our boost/reduce)
+    let llrs = bit_llrs_with_pilot(&scores, pilot, inverted); // soft input for FEC
 
-    let bits = decide_bits(
+    // Version 1 streams carry a CRC-checked header: trust its length instead of guessing, and read the
+    // payload off the medians alone (the vote heuristics below lean towards readable legacy text).
+    // Frames too weak for the recording-level pilot test can still vote a header out of the noise; it
+    // vouches for the recording if its CRC checks out and it names the scheme and frames read with.
+    let stream_bits = hard_bits(&scores, threshold, inverted);
+    let header = if detected {
+        Header::from_bits(&stream_bits[pilot.len()..])
+    } else {
+        let frame_ms = options.frame_duration_ms.unwrap_or(DEFAULT_FRAME_DURATION_MS);
+        Header::from_bits(&stream_bits[pilot.len()..])
+            .filter(|header| header.scheme == scheme_code(scheme.id()) && header.frame_code == frame_code(frame_ms))
+    };
+    if !detected && header.is_none() {
+        return (DecodedWatermark::empty(), DecodeVisualization::empty(first_frame)); // legacy streams need the pilot test
+    }
+    if header.is_some() || Header::has_magic(&stream_bits[pilot.len()..]) {
+        // A damaged version 1 header reads as no message: its bits are not a legacy length and text
+        let decoded = header
+            .and_then(|header| payload_message_with_pilot(header, pilot.len(), &stream_bits, &llrs))
+            .unwrap_or_else(DecodedWatermark::empty);
+        let viz = DecodeVisualization {
+            bit_sequence: stream_bits,
+            scores,
+            votes,
+            threshold,
+            avg_high,
+            avg_low,
+            inverted,
+            llrs,
+            first_frame,
+        };
+        return (decoded, viz);
+    }
+
+    let bits = decide_bits_with_pilot(
         &scores,
         &votes,
+        pilot.len(),
         threshold,
         avg_high,
         avg_low,
         inverted,
     ); // convert scores to bits
 
-    let (_pilot_bits, remainder) = bits.split_at(PILOT_PATTERN.len()); // separate pilot
+    // Legacy stream: search lengths for readable text
+    let (_pilot_bits, remainder) = bits.split_at(pilot.len()); // separate pilot
 
     let (len_bits, data_bits_all) = remainder.split_at(LENGTH_HEADER_BITS.min(remainder.len())); // length header slice
     
@@ -87,7 +469,7 @@ pub fn decode_audio_samples_with_viz(samples: &[f32], sample_rate: u32) -> (Deco
         eprintln!("Length header bits: {}", bits_str);
         
         // Show scores for length header bits
-        let len_start = PILOT_PATTERN.len();
+        let len_start = pilot.len();
         let len_end = len_start + LENGTH_HEADER_BITS;
         eprintln!("Length header scores and votes:");
         for (i, idx) in (len_start..len_end).enumerate() {
@@ -116,12 +498,10 @@ pub fn decode_audio_samples_with_viz(samples: &[f32], sample_rate: u32) -> (Deco
             avg_high,
             avg_low,
             inverted,
+            llrs,
             first_frame,
         };
-        return (DecodedWatermark {
-            message: String::new(),
-            raw_bytes: Vec::new(),
-        }, empty_viz);
+        return (DecodedWatermark::empty(), empty_viz);
     }
 
     // Try every plausible length and pick the one that yields the most readable ASCII.
@@ -143,7 +523,7 @@ pub fn decode_audio_samples_with_viz(samples: &[f32], sample_rate: u32) -> (Deco
         let score =
             printable_ratio * 2.0 + candidate_len as f32 * 0.05 + 0.1 * proximity; // prefer longer printable text
 
-        if best.as_ref().map_or(true, |(s, _)| score > *s) {
+        if best.as_ref().is_none_or(|(s, _)| score > *s) {
             best = Some((score, candidate));
         }
     }
@@ -163,13 +543,12 @@ pub fn decode_audio_samples_with_viz(samples: &[f32], sample_rate: u32) -> (Deco
                 .count() as f32
                 / chosen.raw_bytes.len().max(1) as f32
         );
-        let data_start = PILOT_PATTERN.len() + LENGTH_HEADER_BITS;
+        let data_start = pilot.len() + LENGTH_HEADER_BITS;
         let bits_to_show = (chosen.raw_bytes.len() * 8).min(data_bits_all.len());
         if bits_to_show > 0 {
             eprintln!("First {} data bits (after pilot+length):", bits_to_show);
-            for idx in 0..bits_to_show {
+            for (idx, &bit) in data_bits_all[..bits_to_show].iter().enumerate() {
                 let global_idx = data_start + idx;
-                let bit = data_bits_all[idx];
                 let vote = votes.get(global_idx).copied().unwrap_or(0.0);
                 let score = scores.get(global_idx).copied().unwrap_or(0.0);
                 eprintln!(
@@ -201,18 +580,161 @@ pub fn decode_audio_samples_with_viz(samples: &[f32], sample_rate: u32) -> (Deco
         avg_high,
         avg_low,
         inverted,
+        llrs,
         first_frame,
     };
     
     (chosen, viz)
 }
 
+/// Decoder for schemes that carry a few bits per frame: fold the frames onto the bitstream cycle.
+fn decode_cycle<S: WatermarkScheme + ?Sized>(
+    ctx: &mut FftContext,
+    source: &FrameSource<S>,
+    first_frame: Vec<f32>,
+) -> (DecodedWatermark, DecodeVisualization) {
+    let stream = cycle_scores(ctx, source); // one entry per carried bit position
+    let pilot = &source.pilot;
+    let Some(fold) = fold_cycle(&stream, pilot) else {
+        return (DecodedWatermark::empty(), DecodeVisualization::empty(first_frame)); // no cycle matches the pilot and length
+    };
+
+    let (avg_high, avg_low, threshold) = pilot_stats_with_pilot(&fold.scores, pilot); // fold_cycle only accepts upright polarity
+    let bits = decide_bits_with_pilot(&fold.scores, &fold.votes, pilot.len(), threshold, avg_high, avg_low, false);
+    let llrs = bit_llrs_with_pilot(&fold.scores, pilot, false);
+    let decoded = match fold.header {
+        Some(header) => payload_message_with_pilot(header, pilot.len(), &bits, &llrs).unwrap_or_else(DecodedWatermark::empty),
+        None => bits_to_message(bits[fold.payload_start..].to_vec(), fold.message_bytes), // length is already confirmed
+    };
+
+    let viz = DecodeVisualization {
+        bit_sequence: bits,
+        llrs,
+        scores: fold.scores,
+        votes: fold.votes,
+        threshold,
+        avg_high,
+        avg_low,
+        inverted: false,
+        first_frame,
+    };
+    (decoded, viz)
+}
+
+/// Decoder for the segmented layout: place every frame in the segment cycle, then vote per segment.
+/// `None` if no frame carries a readable segment field.
+fn decode_segments<S: WatermarkScheme + ?Sized>(
+    ctx: &mut FftContext,
+    source: &FrameSource<S>,
+    chunk_bits: usize,
+    first_frame: Vec<f32>,
+) -> Option<(DecodedWatermark, DecodeVisualization)> {
+    let reads = read_frames(ctx, source); // every frame, in order
+    let frames = frame_scores(&reads, source); // accepted frames with their pilot verdicts
+    let pilot = &source.pilot;
+    let (count, offset, segments_seen) = segment_cycle(&frames, pilot.len())?;
+
+    // Reassemble pilot + header + payload positions, collecting every frame's scores for them
+    let field_end = pilot.len() + SEGMENT_FIELD_BITS; // first chunk bit in a frame
+    let stream_len = pilot.len() + count * chunk_bits;
+    let mut score_samples: Vec<Vec<f32>> = vec![Vec::new(); stream_len]; // per-position scores
+    let mut valid_frames = 0usize; // accepted frames
+    let mut inverted_frames = 0usize; // frames whose pilot indicates flipped polarity
+    // (stream position, score) of every pilot and chunk bit of frame `index`
+    let positions = |index: usize, frame: &FrameScores| -> Vec<(usize, f32)> {
+        let chunk_start = pilot.len() + (offset + index) % count * chunk_bits; // this frame's segment
+        let pilot = frame.scores.iter().take(pilot.len()).copied().enumerate();
+        let chunk = frame.scores.iter().skip(field_end).take(chunk_bits).copied().enumerate();
+        pilot.chain(chunk.map(|(bit, score)| (chunk_start + bit, score))).collect()
+    };
+    for (index, frame) in frames.iter().enumerate() {
+        let Some(frame) = frame else {
+            continue; // pilot mismatch or gated out
+        };
+        valid_frames += 1;
+        inverted_frames += usize::from(frame.inverted);
+        for (position, score) in positions(index, frame) {
+            score_samples[position].push(score);
+        }
+    }
+
+    // An excerpt shorter than the cycle cannot be put back together: report the segments it holds
+    if (0..count).any(|segment| score_samples[pilot.len() + segment * chunk_bits].is_empty()) {
+        let decoded = DecodedWatermark { segments_seen, ..DecodedWatermark::empty() };
+        return Some((decoded, DecodeVisualization::empty(first_frame)));
+    }
+
+    let scores: Vec<f32> = score_samples.iter_mut().map(|samples| median(samples).unwrap_or(0.0)).collect();
+
+    // The adaptive curve, fitted to the medians, levels them and every frame's scores before it votes
+    let offsets = if source.options.adaptive_threshold {
+        let bins = position_bins(source, stream_len, Some((field_end, chunk_bits)));
+        threshold::curve_offsets(&scores, &bins, pilot)
+    } else {
+        vec![0.0; stream_len]
+    };
+    let scores: Vec<f32> = scores.iter().zip(&offsets).map(|(score, offset)| score - offset).collect();
+
+    let mut vote_counts = vec![0u32; stream_len]; // per-position “1” votes
+    for (index, frame) in frames.iter().enumerate() {
+        let Some(frame) = frame else {
+            continue;
+        };
+        for (position, score) in positions(index, frame) {
+            let score = score - offsets[position]; // flattened
+            let vote_one = if frame.inverted { score <= frame.threshold } else { score >= frame.threshold };
+            vote_counts[position] += u32::from(vote_one);
+        }
+    }
+    let votes: Vec<f32> = vote_counts
+        .iter()
+        .zip(&score_samples)
+        .map(|(&ones, samples)| ones as f32 / samples.len().max(1) as f32)
+        .collect(); // convert to ratios
+
+    let (avg_high, avg_low, threshold) = pilot_stats_with_pilot(&scores, pilot); // global threshold from pilot
+    let inverted = inverted_frames * 2 >= valid_frames.max(1) || avg_high < avg_low;
+    let header = Header::from_bits(&hard_bits(&scores[pilot.len()..], threshold, inverted));
+    let payload_start = pilot.len() + HEADER_BITS;
+    let header = header.filter(|header| payload_start + fec::header_coded_len(header) <= stream_len);
+
+    let mut bits = decide_bits_with_pilot(&scores, &votes, pilot.len(), threshold, avg_high, avg_low, inverted);
+    let llrs = bit_llrs_with_pilot(&scores, pilot, inverted);
+    if let Some(header) = header {
+        bits.truncate(payload_start + fec::header_coded_len(&header)); // drop the unused end of the last chunk
+    }
+    // Segments that reassemble without a valid header read as no message
+    let decoded = header.and_then(|header| payload_message_with_pilot(header, pilot.len(), &bits, &llrs));
+    let decoded = DecodedWatermark { segments_seen, ..decoded.unwrap_or_else(DecodedWatermark::empty) };
+
+    let viz = DecodeVisualization {
+        bit_sequence: bits,
+        llrs,
+        scores,
+        votes,
+        threshold,
+        avg_high,
+        avg_low,
+        inverted,
+        first_frame,
+    };
+    Some((decoded, viz))
+}
+
 /// Blindly decode the watermark from the provided path.
 pub fn decode_watermarked_sample(path: impl AsRef<Path>) -> DecodedWatermark {
+    decode_watermarked_sample_with_scheme(path, EmbeddingMode::default().scheme())
+}
+
+/// [`decode_watermarked_sample`] with any [`WatermarkScheme`] (used by the CLI's `--scheme`)
+pub fn decode_watermarked_sample_with_scheme<S: WatermarkScheme + ?Sized>(
+    path: impl AsRef<Path>,
+    scheme: &S,
+) -> DecodedWatermark {
     println!("=== Audio Watermark Decoder (Blind) ===\n"); // header
 
     let (samples, sample_rate) = load_audio(path.as_ref()); // load waveform
-    let decoded = decode_audio_samples(&samples, sample_rate);
+    let (decoded, _) = decode_audio_samples_with_scheme(scheme, &samples, sample_rate, &DecodeOptions::default());
     
     println!(
         "\nDecoded message: \"{}\" (bytes: {:?})",
@@ -231,90 +753,90 @@ pub fn default_watermarked_path() -> PathBuf {
 
 // --- Frame analysis helpers -------------------------------------------------
 
-fn summarise_frames(
-    samples: &[f32],
-    sample_rate: u32,
-    window_radius: usize,
-) -> (Vec<f32>, Vec<f32>, usize, usize, bool) {
-    let frame_len = ((sample_rate as f32 * WATERMARK_FRAME_DURATION)
-        .round()
-        .max(1.0)) as usize; // samples per frame
-    let fft_len = frame_len.next_power_of_two().max(2); // FFT size
-
-    let mut planner = RealFftPlanner::<f32>::new(); // FFT planner
-    let forward = planner.plan_fft_forward(fft_len); // forward FFT
-    let mut scratch = forward.make_scratch_vec(); // scratch buffer
-    let mut buffer = vec![0.0f32; fft_len]; // time-domain buffer
-    let mut spectrum = forward.make_output_vec(); // frequency-domain buffer
-
-    let usable_bins = spectrum.len().saturating_sub(START_BIN); // candidate bins
-    let mut score_samples: Vec<Vec<f32>> =
-        (0..usable_bins).map(|_| Vec::with_capacity(128)).collect(); // per-bin scores
-    let mut vote_counts = vec![0u32; usable_bins]; // per-bin “1” votes
-    let mut valid_frames = 0usize; // accepted frames
-    let mut skipped_frames = 0usize; // rejected frames
-    let mut inverted_frames = 0usize; // frames whose pilot indicates flipped polarity
-
-    let mut offset = 0usize; // frame pointer
-    while offset < samples.len() {
-        let end = (offset + frame_len).min(samples.len()); // clamp frame
-        let frame = &samples[offset..end]; // frame view
-
-        buffer.fill(0.0); // clear buffer
-        buffer[..frame.len()].copy_from_slice(frame); // copy samples
+/// Bin (counted from the first scored bin) carrying each of `len` stream positions.
+/// `segments` gives (first chunk position, chunk length) when every frame carries one chunk of a longer stream.
+fn position_bins<S: ?Sized>(source: &FrameSource<S>, len: usize, segments: Option<(usize, usize)>) -> Vec<usize> {
+    let mut bin_of_slot = vec![0usize; source.order.as_ref().map_or(0, Vec::len)];
+    for (bin, &slot) in source.order.iter().flatten().enumerate() {
+        bin_of_slot[slot] = bin; // the interleaver put this slot on that bin
+    }
+    let pilot_len = source.pilot.len();
+    (0..len)
+        .map(|position| match segments {
+            Some((field_end, chunk_bits)) if position >= pilot_len => field_end + (position - pilot_len) % chunk_bits,
+            _ => position,
+        })
+        .map(|slot| bin_of_slot.get(slot).copied().unwrap_or(slot))
+        .collect()
+}
 
-        forward
-            .process_with_scratch(&mut buffer, &mut spectrum, &mut scratch)
-            .expect("FFT failed"); // FFT
+/// The call being decoded, as seen by the per-frame helpers.
+pub(crate) struct FrameSource<'a, S: ?Sized> {
+    pub(crate) scheme: &'a S,
+    pub(crate) samples: &'a [f32],
+    pub(crate) frame_len: usize,
+    pub(crate) sample_rate: u32,
+    pub(crate) options: &'a DecodeOptions,
+    pub(crate) order: Option<Vec<usize>>, // interleaver permutation over the scheme's capacity
+    pub(crate) pilot: Vec<u8>, // the pilot every frame starts with
+    pub(crate) false_alarm: f64, // per frame, or per recording when frames are voted
+    pub(crate) detection: f32, // smallest pilot correlation a frame is accepted at on its own
+    pub(crate) vote: f32, // smallest pilot correlation a frame votes at (repeated layout)
+    pub(crate) first_index: usize, // encoder's index of the first frame in `samples` (modulo the scheme's index period)
+}
 
-        let mut magnitudes = Vec::with_capacity(usable_bins); // magnitude list
-        for idx in 0..usable_bins {
-            let bin = START_BIN + idx; // actual bin
-            if bin >= spectrum.len() {
-                break;
-            }
-            magnitudes.push(spectrum[bin].norm()); // magnitude
-        }
+impl<'a, S: WatermarkScheme + ?Sized> FrameSource<'a, S> {
+    /// `Err` if `options` ask for a pilot, interleaver or false-alarm rate the detector cannot use.
+    pub(crate) fn new(
+        scheme: &'a S,
+        samples: &'a [f32],
+        frame_len: usize,
+        sample_rate: u32,
+        options: &'a DecodeOptions,
+    ) -> Result<Self, String> {
+        let spectrum_len = frame_len.next_power_of_two().max(2) / 2 + 1;
+        let pilot = options.pilot.bits()?;
+        let order = options.interleaver.frame_order(scheme.capacity(spectrum_len), pilot.len())?;
+        let false_alarm = options.false_alarm.unwrap_or(pilot::DEFAULT_FALSE_ALARM);
+        let detection = pilot::detection_threshold(pilot.len(), false_alarm)?;
+        let vote = pilot::detection_threshold(pilot.len(), pilot::VOTE_FALSE_ALARM)?.min(detection);
+        Ok(Self { scheme, samples, frame_len, sample_rate, options, order, pilot, false_alarm, detection, vote, first_index: 0 })
+    }
+}
 
-        if magnitudes.len() < PILOT_PATTERN.len() {
-            skipped_frames += 1; // not enough bins
-            offset += frame_len;
-            continue;
-        }
+/// What the frames that vote agree on.
+struct FrameSummary {
+    scores: Vec<f32>,  // median score per bin
+    votes: Vec<f32>,   // fraction of frames per bin voting "1"
+    inverted: bool,    // majority of frames read with flipped polarity
+    detected: bool,    // the recording-level pilot test passed
+}
 
-        let scores = spectral_scores(&magnitudes, window_radius); // log-normalised scores
-        if let Some((threshold, matches, frame_inverted)) = frame_pilot_stats(&scores) {
-            if matches >= 5 {
-                valid_frames += 1; // accept frame
-                if frame_inverted {
-                    inverted_frames += 1;
-                }
-                for (idx, score) in scores.iter().enumerate() {
-                    if idx >= usable_bins {
-                        break;
-                    }
-                    score_samples[idx].push(*score); // record score
-                    let vote_one = if frame_inverted {
-                        *score <= threshold
-                    } else {
-                        *score >= threshold
-                    };
-                    if vote_one {
-                        vote_counts[idx] += 1; // vote for “1”
-                    }
-                }
-            } else {
-                skipped_frames += 1; // pilot mismatch
-            }
-        } else {
-            skipped_frames += 1; // pilot unusable
-        }
+/// Summarise the frames that vote; `None` if no frame votes.
+fn summarise_frames<S: WatermarkScheme + ?Sized>(
+    ctx: &mut FftContext,
+    source: &FrameSource<S>,
+) -> Option<FrameSummary> {
+    let usable_bins = ctx.buffers.spectrum.len().saturating_sub(START_BIN); // candidate bins
+    let reads = read_frames(ctx, source); // every frame, in order
+    let verdicts: Vec<Option<(f32, bool)>> = reads.iter().map(|read| read.verdict(&source.pilot, source.vote)).collect();
+    let accepted = || reads.iter().zip(&verdicts).filter_map(|(read, verdict)| Some((read, (*verdict)?)));
+    let valid_frames = accepted().count(); // accepted frames
+    let inverted_frames = accepted().filter(|&(_, (_, inverted))| inverted).count(); // flipped polarity
 
-        offset += frame_len; // advance frame pointer
+    if valid_frames == 0 {
+        return None; // no frame looks marked
     }
+    // Whether the recording as a whole looks marked, not just the frames that happened to vote
+    let correlations: Vec<f32> = reads.iter().filter_map(|read| read.correlation).collect(); // every scored frame
+    let detected = pilot::recording_detected(&correlations, source.pilot.len(), source.false_alarm);
 
-    if valid_frames == 0 {
-        panic!("unable to decode watermark: no reliable frames detected");
+    let mut score_samples: Vec<Vec<f32>> =
+        (0..usable_bins).map(|_| Vec::with_capacity(valid_frames)).collect(); // per-bin scores
+    for (read, _) in accepted() {
+        for (idx, score) in read.scores.iter().enumerate().take(usable_bins) {
+            score_samples[idx].push(*score); // record score
+        }
     }
 
     let mut medians = Vec::with_capacity(usable_bins); // aggregated scores
@@ -329,6 +851,29 @@ fn summarise_frames(
         }
     }
 
+    // The adaptive curve, fitted to the medians, levels them and every frame's scores before it votes
+    let offsets = if source.options.adaptive_threshold {
+        threshold::curve_offsets(&medians, &position_bins(source, medians.len(), None), &source.pilot)
+    } else {
+        vec![0.0; medians.len()]
+    };
+    let medians = medians.iter().zip(&offsets).map(|(median, offset)| median - offset).collect();
+
+    let mut vote_counts = vec![0u32; usable_bins]; // per-bin “1” votes
+    for (read, (threshold, frame_inverted)) in accepted() {
+        for (idx, score) in read.scores.iter().enumerate().take(usable_bins) {
+            let score = score - offsets[idx]; // flattened
+            let vote_one = if frame_inverted {
+                score <= threshold
+            } else {
+                score >= threshold
+            };
+            if vote_one {
+                vote_counts[idx] += 1; // vote for “1”
+            }
+        }
+    }
+
     let ratios = vote_counts
         .into_iter()
         .map(|votes| votes as f32 / valid_frames as f32)
@@ -336,19 +881,422 @@ fn summarise_frames(
 
     let inverted = inverted_frames * 2 >= valid_frames.max(1); // majority of frames inverted?
 
-    (medians, ratios, valid_frames, skipped_frames, inverted) // summary
+    Some(FrameSummary { scores: medians, votes: ratios, inverted, detected }) // summary
+}
+
+/// One frame's scores and pilot correlation: what every per-frame stage reads.
+#[derive(Clone, Copy)]
+pub(crate) struct FrameRead<'a> {
+    pub(crate) correlation: Option<f32>, // None when gated out, too quiet or short of bins
+    pub(crate) scores: &'a [f32],
+}
+
+/// Every frame's reads, their scores in one buffer allocated once per decode.
+pub(crate) struct FrameReads {
+    scores: Vec<f32>,                  // frame `i`'s scores start at `i × stride`
+    stride: usize,                     // most scores a frame can have
+    frames: Vec<(Option<f32>, usize)>, // per frame: pilot correlation and score count
+}
+
+impl FrameReads {
+    /// Frame reads in frame order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = FrameRead<'_>> + '_ {
+        self.frames.iter().enumerate().map(|(index, &(correlation, len))| FrameRead {
+            correlation,
+            scores: &self.scores[index * self.stride..index * self.stride + len],
+        })
+    }
+}
+
+impl FrameRead<'_> {
+    /// Frame threshold and polarity when the pilot correlation reaches `min_correlation`.
+    pub(crate) fn verdict(&self, pilot: &[u8], min_correlation: f32) -> Option<(f32, bool)> {
+        frame_verdict(self.scores, pilot, self.correlation?, min_correlation)
+    }
+}
+
+/// Every frame of `source` scored, in frame order (the last may be partial).
+pub(crate) fn read_frames<S: WatermarkScheme + ?Sized>(ctx: &mut FftContext, source: &FrameSource<S>) -> FrameReads {
+    let stride = ctx.buffers.spectrum.len(); // a scheme scores at most one position per bin
+    let frame_count = source.samples.len().div_ceil(source.frame_len);
+    let mut scores = vec![0.0; frame_count * stride];
+    // Copy a frame's scores into its slot; the count kept
+    let keep = |slot: &mut [f32], scores: &[f32]| {
+        let len = scores.len().min(slot.len());
+        slot[..len].copy_from_slice(&scores[..len]);
+        len
+    };
+
+    // Analyse frames on worker threads, each into its own slot, so results match the serial path
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    let frames = {
+        use rayon::prelude::*;
+
+        let plans = &ctx.plans;
+        scores
+            .par_chunks_mut(stride)
+            .zip(source.samples.par_chunks(source.frame_len))
+            .enumerate()
+            .map_init(
+                || FrameBuffers::new(plans),
+                |buffers, (index, (slot, frame))| {
+                    let correlation = frame_correlation(source, plans, buffers, index, frame);
+                    (correlation, keep(slot, &buffers.scores))
+                },
+            )
+            .collect()
+    };
+
+    #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+    let frames = {
+        let mut frames = Vec::with_capacity(frame_count);
+        for (index, (slot, frame)) in scores.chunks_mut(stride).zip(source.samples.chunks(source.frame_len)).enumerate() {
+            let correlation = frame_correlation(source, &ctx.plans, &mut ctx.buffers, index, frame);
+            frames.push((correlation, keep(slot, &ctx.buffers.scores)));
+        }
+        frames
+    };
+
+    FrameReads { scores, stride, frames }
+}
+
+/// Scores one frame into `buffers.scores`; its pilot correlation, or None if too quiet or short.
+fn frame_correlation<S: WatermarkScheme + ?Sized>(
+    source: &FrameSource<S>,
+    plans: &FftPlans,
+    buffers: &mut FrameBuffers,
+    index: usize,
+    frame: &[f32],
+) -> Option<f32> {
+    let pilot = &source.pilot;
+    if !score_frame(source, plans, buffers, index, frame) || buffers.scores.len() < pilot.len() {
+        return None; // too quiet or not enough bins
+    }
+    Some(pilot::correlation(&buffers.scores, pilot)) // sign gives the polarity
+}
+
+/// Frame threshold and polarity when `correlation` reaches `min_correlation`.
+fn frame_verdict(scores: &[f32], pilot: &[u8], correlation: f32, min_correlation: f32) -> Option<(f32, bool)> {
+    if correlation.abs() < min_correlation {
+        return None; // no more like the pilot than unmarked audio would be
+    }
+    let (threshold, _, _) = frame_pilot_stats_with_pilot(scores, pilot)?;
+    Some((threshold, correlation < 0.0))
+}
+
+/// Runs the scheme's analysis over one frame into `buffers.scores`; false if the gate rejects it.
+fn score_frame<S: WatermarkScheme + ?Sized>(
+    source: &FrameSource<S>,
+    plans: &FftPlans,
+    buffers: &mut FrameBuffers,
+    index: usize,
+    frame: &[f32],
+) -> bool {
+    buffers.scores.clear();
+    if source.options.energy_gate.is_some_and(|gate| gate.is_quiet(frame)) {
+        return false; // too quiet to carry data
+    }
+
+    if source.scheme.hann_window() {
+        buffers.forward_hann(plans, frame); // windowed FFT
+    } else {
+        buffers.forward(plans, frame); // FFT
+    }
+
+    buffers.magnitudes.clear(); // magnitude list
+    let first = START_BIN.min(buffers.spectrum.len());
+    buffers
+        .magnitudes
+        .extend(buffers.spectrum[first..].iter().map(|c| c.norm())); // magnitude
+
+    let mut analysis = AnalysisFrame {
+        index: source.first_index + index,
+        sample_rate: source.sample_rate,
+        strength_percent: source.options.strength_percent.unwrap_or(DEFAULT_STRENGTH_PERCENT),
+        spectrum: &buffers.spectrum,
+        magnitudes: &buffers.magnitudes,
+        log_mags: &mut buffers.log_mags,
+        prefix: &mut buffers.prefix,
+    };
+    source.scheme.analyse_frame(&mut analysis, &mut buffers.scores); // per-bit soft scores
+    if let Some(order) = source.order.as_deref().filter(|order| order.len() <= buffers.scores.len()) {
+        interleave::deinterleave_into(&buffers.scores, order, &mut buffers.reordered); // back to stream order
+        std::mem::swap(&mut buffers.scores, &mut buffers.reordered);
+    }
+    true
+}
+
+/// One frame whose pilot was accepted.
+pub(crate) struct FrameScores<'a> {
+    pub(crate) threshold: f32, // from the frame's own pilot
+    pub(crate) inverted: bool, // frame polarity
+    pub(crate) scores: &'a [f32],
+}
+
+/// Every frame's scores and pilot verdict, in frame order (None where the pilot was rejected).
+pub(crate) fn frame_scores<'a, S: WatermarkScheme + ?Sized>(
+    reads: &'a FrameReads,
+    source: &FrameSource<S>,
+) -> Vec<Option<FrameScores<'a>>> {
+    reads
+        .iter()
+        .map(|read| {
+            let (threshold, inverted) = read.verdict(&source.pilot, source.detection)?;
+            Some(FrameScores { threshold, inverted, scores: read.scores })
+        })
+        .collect()
+}
+
+/// `(count, offset, seen)` of the segment cycle: frame `i` carries segment `(offset + i) mod count`.
+/// Count and offset are majority votes over the frames whose segment field passes its CRC;
+/// `seen` lists the indices those frames named (sorted).
+fn segment_cycle(frames: &[Option<FrameScores>], pilot_len: usize) -> Option<(usize, usize, Vec<usize>)> {
+    let fields: Vec<(usize, SegmentField)> = frames
+        .iter()
+        .enumerate()
+        .filter_map(|(index, frame)| {
+            let frame = frame.as_ref()?;
+            let field_scores = frame.scores.get(pilot_len..pilot_len + SEGMENT_FIELD_BITS)?;
+            let field = SegmentField::from_bits(&hard_bits(field_scores, frame.threshold, frame.inverted))?;
+            Some((index, field))
+        })
+        .collect();
+
+    let count = majority(fields.iter().map(|(_, field)| field.count))?;
+    let in_cycle = || fields.iter().filter(|(_, field)| field.count == count);
+    let offset = majority(in_cycle().map(|(index, field)| (field.index + count - index % count) % count))?;
+    let mut seen: Vec<usize> = in_cycle().map(|(_, field)| field.index).collect();
+    seen.sort_unstable();
+    seen.dedup();
+    Some((count, offset, seen))
+}
+
+/// Sample offset in `0..frame_len` that puts the frame grid back on the encoder's frames, and the
+/// encoder's index of the first frame from there (modulo the scheme's index period).
+/// Each candidate is scored by the pilot separation of the frames that would vote,
+/// over a few frames spread through the excerpt; ties go to the earliest offset and phase.
+pub(crate) fn frame_alignment<S: WatermarkScheme + ?Sized>(
+    ctx: &mut FftContext,
+    source: &FrameSource<S>,
+) -> (usize, usize) {
+    let frame_len = source.frame_len;
+    let whole_frames = source.samples.len().saturating_sub(frame_len) / frame_len; // complete at every offset
+    if whole_frames == 0 {
+        return (0, 0); // nothing to compare
+    }
+    let probes: Vec<usize> = (0..whole_frames).step_by(whole_frames.div_ceil(ALIGN_PROBE_FRAMES)).collect();
+    let candidates = frame_len * source.scheme.index_period(); // (offset, phase) as phase × frame_len + offset
+
+    let pilot_strength = |plans: &FftPlans, buffers: &mut FrameBuffers, candidate: usize| -> f32 {
+        let (offset, phase) = (candidate % frame_len, candidate / frame_len);
+        probes
+            .iter()
+            .map(|&index| {
+                let start = offset + index * frame_len;
+                let frame = &source.samples[start..start + frame_len];
+                let correlation = frame_correlation(source, plans, buffers, phase + index, frame);
+                match correlation.and_then(|correlation| frame_verdict(&buffers.scores, &source.pilot, correlation, source.vote)) {
+                    Some(_) => {
+                        let (avg_high, avg_low, _) = pilot_stats_with_pilot(&buffers.scores, &source.pilot);
+                        (avg_high - avg_low).abs()
+                    }
+                    None => 0.0, // pilot not found at this offset
+                }
+            })
+            .sum()
+    };
+
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    let strengths: Vec<f32> = {
+        use rayon::prelude::*;
+
+        let plans = &ctx.plans;
+        (0..candidates)
+            .into_par_iter()
+            .map_init(|| FrameBuffers::new(plans), |buffers, candidate| pilot_strength(plans, buffers, candidate))
+            .collect()
+    };
+
+    #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+    let strengths: Vec<f32> = (0..candidates)
+        .map(|candidate| pilot_strength(&ctx.plans, &mut ctx.buffers, candidate))
+        .collect();
+
+    let mut best = 0usize;
+    for (candidate, &strength) in strengths.iter().enumerate() {
+        if strength > strengths[best] {
+            best = candidate;
+        }
+    }
+    (best % frame_len, best / frame_len)
+}
+
+/// Most frequent value (ties go to the smallest).
+fn majority(values: impl Iterator<Item = usize>) -> Option<usize> {
+    let mut tally = std::collections::BTreeMap::new();
+    for value in values {
+        *tally.entry(value).or_insert(0usize) += 1;
+    }
+    tally
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+        .map(|(value, _)| value)
+}
+
+/// Median of `values` (reordered in place), None if empty.
+pub(crate) fn median(values: &mut [f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    let mid = values.len() / 2; // median index
+    let (_, median, _) = values.select_nth_unstable_by(mid, |a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    Some(*median)
+}
+
+/// Per-position scores for a cycling scheme: frame `i` fills positions `i × capacity ..`.
+/// Frames that are gated out or score short leave `None`s.
+fn cycle_scores<S: WatermarkScheme + ?Sized>(ctx: &mut FftContext, source: &FrameSource<S>) -> Vec<Option<f32>> {
+    let capacity = source.scheme.capacity(ctx.buffers.spectrum.len()); // bits per frame
+    if capacity == 0 {
+        return Vec::new(); // nothing to read
+    }
+    let frames = source.samples.chunks_exact(source.frame_len); // partial tail frame carries too little
+    let mut stream = vec![None; frames.len() * capacity]; // gated or short frames stay None, keeping later ones aligned
+    let fill = |slot: &mut [Option<f32>], scored: bool, scores: &[f32]| {
+        if scored && scores.len() >= capacity {
+            slot.iter_mut().zip(scores).for_each(|(position, &score)| *position = Some(score));
+        }
+    };
+
+    // Each frame writes its own slot of the stream, so the threads need no scratch copies
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    {
+        use rayon::prelude::*;
+
+        let plans = &ctx.plans;
+        stream
+            .par_chunks_mut(capacity)
+            .zip(source.samples.par_chunks_exact(source.frame_len))
+            .enumerate()
+            .for_each_init(
+                || FrameBuffers::new(plans),
+                |buffers, (index, (slot, frame))| {
+                    let scored = score_frame(source, plans, buffers, index, frame);
+                    fill(slot, scored, &buffers.scores);
+                },
+            );
+    }
+
+    #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+    for (index, (slot, frame)) in stream.chunks_mut(capacity).zip(frames).enumerate() {
+        let scored = score_frame(source, &ctx.plans, &mut ctx.buffers, index, frame);
+        fill(slot, scored, &ctx.buffers.scores);
+    }
+
+    stream
+}
+
+/// Scores of a cycling scheme folded onto one bitstream cycle.
+struct CycleFold {
+    scores: Vec<f32>,        // median score per bitstream position
+    votes: Vec<f32>,         // fraction of frames per position above the threshold
+    payload_start: usize,    // first payload position (after pilot and header)
+    message_bytes: usize,    // bytes the cycle carries after `payload_start` (coded, if the header names a code)
+    header: Option<Header>,  // None for legacy streams
+}
+
+/// Find the cycle length whose fold reproduces the pilot and a header describing that length.
+fn fold_cycle(stream: &[Option<f32>], pilot: &[u8]) -> Option<CycleFold> {
+    let shortest = pilot.len() + LENGTH_HEADER_BITS; // legacy stream, empty message
+    let longest = stream.len().min(pilot.len() + HEADER_BITS + 8 * MAX_CYCLE_MESSAGE_BYTES);
+
+    (shortest..=longest).step_by(8).find_map(|cycle| {
+        let scores = fold_medians(stream, cycle)?;
+        let (avg_high, avg_low, threshold) = pilot_stats_with_pilot(&scores, pilot);
+        if avg_high <= avg_low {
+            return None; // cycling schemes do not flip polarity
+        }
+
+        let bits = hard_bits(&scores, threshold, false);
+        if bits[..pilot.len()] != *pilot {
+            return None; // fold is not aligned with the bitstream
+        }
+        let after_pilot = &bits[pilot.len()..];
+        let (header, payload_start, message_bytes) = match Header::from_bits(after_pilot) {
+            Some(header) => (Some(header), pilot.len() + HEADER_BITS, fec::header_coded_len(&header) / 8),
+            None => (None, shortest, decode_length_header(&after_pilot[..LENGTH_HEADER_BITS])),
+        };
+        if payload_start + 8 * message_bytes != cycle {
+            return None; // header describes a different cycle
+        }
+
+        let votes = fold_votes(stream, cycle, threshold);
+        Some(CycleFold {
+            scores,
+            votes,
+            payload_start,
+            message_bytes,
+            header,
+        })
+    })
+}
+
+/// Median of the scores landing on each of `cycle` positions (None if a position has none).
+fn fold_medians(stream: &[Option<f32>], cycle: usize) -> Option<Vec<f32>> {
+    let mut buckets = vec![Vec::new(); cycle];
+    for (position, score) in stream.iter().enumerate() {
+        if let Some(score) = score {
+            buckets[position % cycle].push(*score);
+        }
+    }
+    buckets.iter_mut().map(|bucket| median(bucket)).collect() // None if a position was never observed
 }
 
-fn spectral_scores(magnitudes: &[f32], window_radius: usize) -> Vec<f32> {
+/// Fraction of scores per cycle position at or above `threshold`.
+fn fold_votes(stream: &[Option<f32>], cycle: usize, threshold: f32) -> Vec<f32> {
+    let mut above = vec![0usize; cycle];
+    let mut total = vec![0usize; cycle];
+    for (position, score) in stream.iter().enumerate() {
+        if let Some(score) = score {
+            total[position % cycle] += 1;
+            above[position % cycle] += usize::from(*score >= threshold);
+        }
+    }
+    above
+        .iter()
+        .zip(&total)
+        .map(|(&a, &t)| a as f32 / t.max(1) as f32)
+        .collect()
+}
+
+/// Log magnitude of each bin relative to the average of its `window_radius` neighbours.
+pub fn spectral_scores(magnitudes: &[f32], window_radius: usize) -> Vec<f32> {
+    let mut log_mags = Vec::with_capacity(magnitudes.len());
+    let mut prefix = Vec::with_capacity(magnitudes.len() + 1);
+    let mut scores = Vec::with_capacity(magnitudes.len());
+    spectral_scores_into(magnitudes, window_radius, &mut log_mags, &mut prefix, &mut scores);
+    scores
+}
+
+/// `spectral_scores` written into caller-owned buffers (no allocation once they are warm).
+pub fn spectral_scores_into(
+    magnitudes: &[f32],
+    window_radius: usize,
+    log_mags: &mut Vec<f32>,
+    prefix: &mut Vec<f64>,
+    scores: &mut Vec<f32>,
+) {
     let epsilon = 1e-12f32; // avoid log(0)
-    let log_mags: Vec<f32> = magnitudes.iter().map(|&v| v.max(epsilon).ln()).collect(); // log spectrum
+    log_mags.clear();
+    log_mags.extend(magnitudes.iter().map(|&v| v.max(epsilon).ln())); // log spectrum
 
-    let mut prefix = vec![0f64; log_mags.len() + 1]; // prefix sums
+    prefix.clear();
+    prefix.push(0.0); // prefix sums
     for (idx, &value) in log_mags.iter().enumerate() {
-        prefix[idx + 1] = prefix[idx] + value as f64;
+        prefix.push(prefix[idx] + value as f64);
     }
 
-    let mut scores = Vec::with_capacity(log_mags.len()); // output
+    scores.clear(); // output
     for (idx, &value) in log_mags.iter().enumerate() {
         let start = idx.saturating_sub(window_radius);
         let end = (idx + window_radius + 1).min(log_mags.len());
@@ -360,22 +1308,26 @@ fn spectral_scores(magnitudes: &[f32], window_radius: usize) -> Vec<f32> {
         let baseline = (prefix[end] - prefix[start] - value as f64) / neighbours.max(1) as f64; // neighbour average
         scores.push(value - baseline as f32); // relative score
     }
+}
 
-    scores
+/// Per-frame pilot check: `(threshold, matches, inverted)`.
+pub fn frame_pilot_stats(scores: &[f32]) -> Option<(f32, usize, bool)> {
+    frame_pilot_stats_with_pilot(scores, &PILOT_PATTERN)
 }
 
-fn frame_pilot_stats(scores: &[f32]) -> Option<(f32, usize, bool)> {
-    if scores.len() < PILOT_PATTERN.len() {
+/// Per-frame pilot check against `pilot`: `(threshold, matches, inverted)`.
+pub fn frame_pilot_stats_with_pilot(scores: &[f32], pilot: &[u8]) -> Option<(f32, usize, bool)> {
+    if scores.len() < pilot.len() {
         return None; // insufficient bins
     }
 
-    let pilot = &scores[..PILOT_PATTERN.len()];
+    let pilot_scores = &scores[..pilot.len()];
     let mut sum_high = 0.0f32;
     let mut sum_low = 0.0f32;
     let mut count_high = 0usize;
     let mut count_low = 0usize;
 
-    for (score, expected) in pilot.iter().zip(PILOT_PATTERN.iter()) {
+    for (score, expected) in pilot_scores.iter().zip(pilot) {
         if *expected == 1 {
             sum_high += score;
             count_high += 1;
@@ -392,14 +1344,14 @@ fn frame_pilot_stats(scores: &[f32]) -> Option<(f32, usize, bool)> {
     let threshold = (sum_high / count_high as f32 + sum_low / count_low as f32) * 0.5; // per-frame decision
 
     // Evaluate both normal and inverted polarity; pick whichever matches pilot better.
-    let matches_normal = pilot
+    let matches_normal = pilot_scores
         .iter()
-        .zip(PILOT_PATTERN.iter())
+        .zip(pilot)
         .filter(|(score, expected)| u8::from(**score >= threshold) == **expected)
         .count();
-    let matches_inverted = pilot
+    let matches_inverted = pilot_scores
         .iter()
-        .zip(PILOT_PATTERN.iter())
+        .zip(pilot)
         .filter(|(score, expected)| u8::from(**score <= threshold) == **expected)
         .count();
 
@@ -410,14 +1362,20 @@ fn frame_pilot_stats(scores: &[f32]) -> Option<(f32, usize, bool)> {
     }
 }
 
-fn pilot_stats(scores: &[f32]) -> (f32, f32, f32) {
-    let pilot = &scores[..PILOT_PATTERN.len()];
+/// Global `(avg_high, avg_low, threshold)` from the pilot bins.
+pub fn pilot_stats(scores: &[f32]) -> (f32, f32, f32) {
+    pilot_stats_with_pilot(scores, &PILOT_PATTERN)
+}
+
+/// Global `(avg_high, avg_low, threshold)` from the bins carrying `pilot`.
+pub fn pilot_stats_with_pilot(scores: &[f32], pilot: &[u8]) -> (f32, f32, f32) {
+    let pilot_scores = &scores[..pilot.len()];
     let mut sum_high = 0.0f32;
     let mut sum_low = 0.0f32;
     let mut count_high = 0usize;
     let mut count_low = 0usize;
 
-    for (score, expected) in pilot.iter().zip(PILOT_PATTERN.iter()) {
+    for (score, expected) in pilot_scores.iter().zip(pilot) {
         if *expected == 1 {
             sum_high += score;
             count_high += 1;
@@ -433,15 +1391,87 @@ fn pilot_stats(scores: &[f32]) -> (f32, f32, f32) {
     (avg_high, avg_low, threshold)
 }
 
-fn decide_bits(
+/// Per-position log-likelihood ratios behind the default pilot; see `bit_llrs_with_pilot`.
+pub fn bit_llrs(scores: &[f32], inverted: bool) -> Vec<f32> {
+    bit_llrs_with_pilot(scores, &PILOT_PATTERN, inverted)
+}
+
+/// Per-position log-likelihood ratios `ln p(score | 1) - ln p(score | 0)` for aggregated scores.
+///
+/// The two classes are modelled as Gaussians with the pilot's "1" and "0" means and their pooled
+/// spread, so the ratio is linear in the score and changes sign at the pilot threshold; `inverted`
+/// flips it as it does the hard decisions. A perfectly clean pilot would give an infinite ratio,
+/// so the spread is floored at a fraction of the class separation and the result clamped.
+pub fn bit_llrs_with_pilot(scores: &[f32], pilot: &[u8], inverted: bool) -> Vec<f32> {
+    if scores.len() < pilot.len() {
+        return Vec::new();
+    }
+    let (avg_high, avg_low, threshold) = pilot_stats_with_pilot(scores, pilot);
+    let separation = (avg_high - avg_low).abs();
+    if separation.is_nan() || separation <= f32::EPSILON {
+        return vec![0.0; scores.len()]; // the pilot says nothing about either class (or lacks one)
+    }
+
+    let squared_error: f32 = scores
+        .iter()
+        .zip(pilot)
+        .map(|(&score, &expected)| (score - if expected == 1 { avg_high } else { avg_low }).powi(2))
+        .sum();
+    let variance = (squared_error / pilot.len().saturating_sub(2).max(1) as f32) // two means fitted
+        .max((LLR_MIN_SPREAD * separation).powi(2));
+    let slope = if inverted { -separation } else { separation } / variance;
+
+    scores
+        .iter()
+        .map(|&score| (slope * (score - threshold)).clamp(-LLR_LIMIT, LLR_LIMIT))
+        .collect()
+}
+
+/// Message of a version 1 stream behind the default pilot; see `payload_message_with_pilot`.
+pub fn payload_message(header: Header, bits: &[u8], llrs: &[f32]) -> Option<DecodedWatermark> {
+    payload_message_with_pilot(header, PILOT_PATTERN.len(), bits, llrs)
+}
+
+/// Message of a version 1 stream behind a `pilot_len`-bit pilot, its payload decoded through the header's FEC (`None` if the stream is too short).
+/// Uncoded payloads take the voted `bits`; coded ones are Viterbi-decoded from the `llrs`.
+pub fn payload_message_with_pilot(header: Header, pilot_len: usize, bits: &[u8], llrs: &[f32]) -> Option<DecodedWatermark> {
+    let payload_start = pilot_len + HEADER_BITS;
+    let payload_end = payload_start + fec::header_coded_len(&header);
+    let data_bits = match Fec::from_header(&header) {
+        Fec::None => bits.get(payload_start..payload_end)?.to_vec(),
+        code => fec::decode(llrs.get(payload_start..payload_end)?, code, header.length * 8),
+    };
+    let mut decoded = bits_to_message(data_bits, header.length);
+    decoded.header = Some(header);
+    Some(decoded)
+}
+
+/// Turn aggregated scores and vote ratios into hard bits (the length header follows the pilot).
+pub fn decide_bits(
     scores: &[f32],
     votes: &[f32],
     threshold: f32,
     avg_high: f32,
     avg_low: f32,
     inverted: bool,
+) -> Vec<u8> {
+    decide_bits_with_pilot(scores, votes, PILOT_PATTERN.len(), threshold, avg_high, avg_low, inverted)
+}
+
+/// Turn aggregated scores and vote ratios into hard bits (the length header follows a `pilot_len`-bit pilot).
+/// The stricter length-header rule applies only to legacy streams: a version 1 header has its own CRC.
+pub fn decide_bits_with_pilot(
+    scores: &[f32],
+    votes: &[f32],
+    pilot_len: usize,
+    threshold: f32,
+    avg_high: f32,
+    avg_low: f32,
+    inverted: bool,
 ) -> Vec<u8> {
     let decision_band = (avg_high - avg_low).abs() * 0.1; // hysteresis
+    let magic = scores.get(pilot_len..pilot_len + 3).unwrap_or(&[]);
+    let legacy = !Header::has_magic(&hard_bits(magic, threshold, inverted)); // no version 1 header
 
     // In some signals the boosted bins end up lower than the reduced ones (phase/energy quirks).
     // When that happens, treat scores below the threshold as "1" and flip vote ratios accordingly.
@@ -467,8 +1497,8 @@ fn decide_bits(
             };
 
             let in_length_header =
-                (PILOT_PATTERN.len()..PILOT_PATTERN.len() + LENGTH_HEADER_BITS).contains(&idx); // header segments
-            let bit = if in_length_header {
+                legacy && (pilot_len..pilot_len + LENGTH_HEADER_BITS).contains(&idx); // header segments
+            if in_length_header {
                 u8::from(effective_ratio >= 0.54 && bit_is_one)
             } else if bit_is_one {
                 1 // confident one
@@ -477,15 +1507,26 @@ fn decide_bits(
             } else {
                 u8::from(effective_ratio >= 0.45 || soft_cmp)
                 // soft fallback
-            };
-            bit
+            }
         })
         .collect()
 }
 
 // --- Bitstream utilities ----------------------------------------------------
 
-fn decode_length_header(bits: &[u8]) -> usize {
+/// Plain threshold decisions (no votes or hysteresis), as used for the CRC-checked header.
+pub fn hard_bits(scores: &[f32], threshold: f32, inverted: bool) -> Vec<u8> {
+    let mut bits = Vec::with_capacity(scores.len());
+    hard_bits_into(scores, threshold, inverted, &mut bits);
+    bits
+}
+
+/// [`hard_bits`] appended to `bits`, for callers collecting many frames in one buffer.
+pub fn hard_bits_into(scores: &[f32], threshold: f32, inverted: bool, bits: &mut Vec<u8>) {
+    bits.extend(scores.iter().map(|&score| u8::from(if inverted { score <= threshold } else { score >= threshold })));
+}
+
+pub fn decode_length_header(bits: &[u8]) -> usize {
     let mut len = 0u16;
     for bit in bits {
         len = (len << 1) | u16::from(bit & 1); // shift & merge
@@ -493,7 +1534,7 @@ fn decode_length_header(bits: &[u8]) -> usize {
     len as usize
 }
 
-fn bits_to_message(bits: Vec<u8>, expected_bytes: usize) -> DecodedWatermark {
+pub(crate) fn bits_to_message(bits: Vec<u8>, expected_bytes: usize) -> DecodedWatermark {
     let mut bytes = Vec::with_capacity(expected_bytes);
     
     // Process exactly expected_bytes worth of bits (8 bits per byte)
@@ -512,7 +1553,7 @@ fn bits_to_message(bits: Vec<u8>, expected_bytes: usize) -> DecodedWatermark {
                 byte = (byte << 1) | (bits[bit_pos] & 1);
             } else {
                 // Pad with zeros if we run out of bits
-                byte = byte << 1;
+                byte <<= 1;
             }
         }
         bytes.push(byte);
@@ -521,6 +1562,10 @@ fn bits_to_message(bits: Vec<u8>, expected_bytes: usize) -> DecodedWatermark {
     DecodedWatermark {
         message: String::from_utf8_lossy(&bytes).into_owned(),
         raw_bytes: bytes,
+        header: None,
+        segments_seen: Vec::new(),
+        authenticated: None,
+        decrypted: None,
     }
 }
 
@@ -0,0 +1,98 @@
+use realfft::num_complex::Complex;
+
+use crate::scheme::{AnalysisFrame, Domain, EmbedFrame, WatermarkScheme};
+
+// =============================================================================
+// Echo hiding - one bit per frame as a faint echo at one of two delays
+// =============================================================================
+//
+// Unlike the other schemes this works in the time domain: every sample gets an
+// echo of `strength / 2` at delay d0 (bit 0) or d1 (bit 1), with the two echo
+// kernels cross-faded over the start of each frame so the delay never jumps.
+// Frame `i` carries bit `i mod bits.len()`, so the whole bitstream repeats as
+// a cycle and the decoder votes across cycles.
+//
+// The decoder reads each frame's real cepstrum (IFFT of log|FFT|), Hann-windowed
+// first: an echo of delay d puts a peak of roughly its amplitude at quefrency d,
+// so c[d1] - c[d0] is positive for a 1 and negative for a 0. Only two quefrencies
+// are needed, so they are summed directly instead of running a second FFT.
+
+// Echo delays in milliseconds (bit 0, bit 1): short enough to be heard as colouration, not as an echo.
+const DELAY_MS: (f32, f32) = (1.0, 1.5);
+
+// Fraction of each frame spent cross-fading from the previous frame's delay.
+const RAMP_FRACTION: usize = 8;
+
+/// Echo delays in samples (bit 0, bit 1) at `sample_rate`.
+pub fn echo_delays(sample_rate: u32) -> (usize, usize) {
+    let to_samples = |ms: f32| ((sample_rate as f32 * ms / 1000.0).round() as usize).max(1);
+    (to_samples(DELAY_MS.0), to_samples(DELAY_MS.1))
+}
+
+/// Real cepstrum of a frame at quefrency `delay`, from its half spectrum.
+pub fn cepstrum_at(spectrum: &[Complex<f32>], delay: usize) -> f32 {
+    let last = spectrum.len() - 1;
+    let fft_len = last * 2;
+    let step = std::f32::consts::TAU * delay as f32 / fft_len as f32;
+    let sum: f32 = spectrum
+        .iter()
+        .enumerate()
+        .map(|(k, bin)| {
+            let weight = if k == 0 || k == last { 1.0 } else { 2.0 }; // DC and Nyquist appear once
+            weight * bin.norm().max(1e-9).ln() * (step * k as f32).cos()
+        })
+        .sum();
+    sum / fft_len as f32
+}
+
+/// Echo hiding: one bit per frame, cycling through the bitstream.
+pub struct EchoScheme;
+
+impl WatermarkScheme for EchoScheme {
+    fn id(&self) -> &'static str {
+        "echo"
+    }
+
+    fn capacity(&self, _spectrum_len: usize) -> usize {
+        1
+    }
+
+    fn domain(&self) -> Domain {
+        Domain::Time
+    }
+
+    fn hann_window(&self) -> bool {
+        true // limits the smearing of the frame edges into the cepstrum
+    }
+
+    fn embed_frame(&self, frame: &mut EmbedFrame<'_>) {
+        let (d0, d1) = echo_delays(frame.sample_rate);
+        let amplitude = frame.strength * 0.5;
+        let ramp = (frame.samples.len() / RAMP_FRACTION).max(1);
+        let bit_at = |index: usize| f32::from(frame.bits[index % frame.bits.len()]);
+
+        // Weight of the d1 kernel: this frame's bit, ramped in from the previous frame's
+        let target = bit_at(frame.index);
+        let previous = if frame.index > 0 { bit_at(frame.index - 1) } else { target };
+
+        for (pos, out) in frame.samples.iter_mut().enumerate() {
+            let n = frame.start + pos;
+            let mix = if pos < ramp {
+                previous + (target - previous) * (pos as f32 / ramp as f32)
+            } else {
+                target
+            };
+            let echo0 = n.checked_sub(d0).map_or(0.0, |i| frame.host[i]);
+            let echo1 = n.checked_sub(d1).map_or(0.0, |i| frame.host[i]);
+            *out = frame.host[n] + amplitude * (mix * echo1 + (1.0 - mix) * echo0);
+        }
+    }
+
+    fn analyse_frame(&self, frame: &mut AnalysisFrame<'_>, scores: &mut Vec<f32>) {
+        let (d0, d1) = echo_delays(frame.sample_rate);
+        scores.clear();
+        if d1 < frame.spectrum.len() - 1 {
+            scores.push(cepstrum_at(frame.spectrum, d1) - cepstrum_at(frame.spectrum, d0));
+        }
+    }
+}
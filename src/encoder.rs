@@ -1,24 +1,41 @@
 use hound::{WavReader, WavWriter};
-use realfft::RealFftPlanner;
+use realfft::num_complex::Complex;
 use std::borrow::Cow;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::additive::AdditiveScheme;
+use crate::echo::EchoScheme;
+use crate::phase::PhaseScheme;
+use crate::qim::QimScheme;
+use crate::energy::{self, EnergyGate, QuietFramePolicy};
+use crate::engine::{Engine, FftPlans, FrameBuffers};
+use crate::auth;
+use crate::cipher;
+use crate::decoder::LENGTH_HEADER_BITS;
+use crate::fec::{self, Fec};
+use crate::interleave::{self, Interleaver};
+use crate::packing::{self, Packing};
+use crate::pilot::Pilot;
+use crate::header::{Header, FLAG_AUTHENTICATED, FLAG_ENCRYPTED, FLAG_PACKED, FLAG_SEGMENTED, MAX_PAYLOAD_BYTES};
+use crate::scheme::{self, Domain, EmbedFrame, MultiplicativeScheme, WatermarkScheme};
+use crate::segment;
+
 // =============================================================================
 // CONSTANTS - Watermark configuration
 // =============================================================================
 
-// Pilot pattern: A known sequence at the start to help decoder find the threshold
-// Alternating 0s and 1s give us clear separation between high and low magnitudes
-pub const PILOT_PATTERN: [u8; 8] = [0, 1, 0, 1, 0, 1, 0, 1];
+pub use crate::pilot::PILOT_PATTERN;
+pub use crate::scheme::START_BIN;
 
-const START_BIN: usize = 48; // embed starting away from low frequencies to reduce audibility
+pub const DEFAULT_STRENGTH_PERCENT: u32 = 15;
 
 // Sample normalization divisor for i16 -> f32 conversion
 const SAMPLE_DIVISOR: f32 = 32768.0;
 
-const SAMPLE_RATES: [u32; 3] = [8000, 16_000, 32_000];
-const FRAME_DURATIONS_MS: [u32; 3] = [20, 32, 64];
+// Experiment grid (also used by the benchmark suite)
+pub const SAMPLE_RATES: [u32; 3] = [8000, 16_000, 32_000];
+pub const FRAME_DURATIONS_MS: [u32; 3] = [20, 32, 64];
 const WATERMARK_STRENGTHS: [u32; 4] = [5, 15, 30, 50];
 
 // Input and output file paths
@@ -43,6 +60,110 @@ pub struct EncodeVisualization {
     pub bit_sequence: Vec<u8>,
 }
 
+/// How bits are written into the watermark bins
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmbeddingMode {
+    /// Scale "1" bins up and "0" bins down (needs existing energy in the bins)
+    #[default]
+    Multiplicative,
+    /// Add a masked, random-phase carrier to "1" bins (works on silent and tonal material)
+    Additive,
+    /// Quantise log magnitudes onto one of two dithered lattices (see [`crate::qim`])
+    Qim,
+    /// Set carrier bin phases to ±π/2, leaving magnitudes alone (see [`crate::phase`])
+    Phase,
+    /// Add a faint echo at one of two delays per frame, in the time domain (see [`crate::echo`])
+    Echo,
+}
+
+impl EmbeddingMode {
+    pub const ALL: [EmbeddingMode; 5] = [
+        EmbeddingMode::Multiplicative,
+        EmbeddingMode::Additive,
+        EmbeddingMode::Qim,
+        EmbeddingMode::Phase,
+        EmbeddingMode::Echo,
+    ];
+
+    /// The built-in [`WatermarkScheme`] behind this mode.
+    pub fn scheme(self) -> &'static dyn WatermarkScheme {
+        match self {
+            EmbeddingMode::Multiplicative => &MultiplicativeScheme,
+            EmbeddingMode::Additive => &AdditiveScheme,
+            EmbeddingMode::Qim => &QimScheme,
+            EmbeddingMode::Phase => &PhaseScheme,
+            EmbeddingMode::Echo => &EchoScheme,
+        }
+    }
+}
+
+/// Look a mode up by its scheme id ("multiplicative", "additive", "qim", "phase", "echo").
+impl std::str::FromStr for EmbeddingMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.scheme().id().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown watermark scheme {name:?}"))
+    }
+}
+
+/// How the bitstream is laid out over frames
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PayloadLayout {
+    /// Every frame carries the whole bitstream (cut to the frame's capacity)
+    #[default]
+    Repeated,
+    /// Header and payload split over consecutive frames, the cycle repeating (see [`crate::segment`]).
+    /// Best with QIM or phase coding; schemes with too few bits per frame keep the repeated layout.
+    Segmented,
+}
+
+/// Encoder settings beyond the message itself
+#[derive(Clone, Debug)]
+pub struct EncodeOptions {
+    pub frame_duration_ms: u32,
+    pub strength_percent: u32,
+    pub mode: EmbeddingMode,
+    pub layout: PayloadLayout,
+    pub energy_gate: Option<EnergyGate>, // how to treat quiet frames (None = embed everywhere)
+    pub interleaver: Interleaver, // spread each frame's bits over the band (the decoder needs the same)
+    pub packing: Packing, // fewer bits per character for restricted text
+    pub encryption_key: Option<Vec<u8>>, // shared secret: encrypt the payload
+    pub auth_key: Option<Vec<u8>>, // shared secret: append an HMAC tag to the payload
+    pub fec: Fec, // convolutional code over the payload, named in the header
+    pub pilot: Pilot, // sync sequence ahead of every frame's bits (the decoder needs the same)
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            frame_duration_ms: 32,
+            strength_percent: DEFAULT_STRENGTH_PERCENT,
+            mode: EmbeddingMode::Multiplicative,
+            layout: PayloadLayout::Repeated,
+            energy_gate: None,
+            interleaver: Interleaver::None,
+            packing: Packing::None,
+            encryption_key: None,
+            auth_key: None,
+            fec: Fec::None,
+            pilot: Pilot::Alternating,
+        }
+    }
+}
+
+impl EncodeOptions {
+    pub fn new(frame_duration_ms: u32, strength_percent: u32) -> Self {
+        Self {
+            frame_duration_ms,
+            strength_percent,
+            ..Self::default()
+        }
+    }
+}
+
 /// WASM-compatible encoder that accepts audio samples directly
 /// Returns encoded samples as Vec<f32>
 pub fn encode_audio_samples(
@@ -64,12 +185,94 @@ pub fn encode_audio_samples_with_viz(
     frame_duration_ms: u32,
     strength_percent: u32,
 ) -> (Vec<f32>, EncodeVisualization) {
-    let strength_percent = strength_percent.max(15); // enforce a floor so the watermark survives noisy audio
+    let options = EncodeOptions::new(frame_duration_ms, strength_percent);
+    encode_audio_samples_with_options(samples, sample_rate, message, &options).expect("unkeyed messages always encode")
+}
+
+/// Encoder taking the full set of [`EncodeOptions`]
+/// Errors if a packed, coded or keyed message is too long for a header (see [`check_message`]) or, sent
+/// whole, for a frame, or if the pilot or interleaver settings are invalid.
+pub fn encode_audio_samples_with_options(
+    samples: &[f32],
+    sample_rate: u32,
+    message: &str,
+    options: &EncodeOptions,
+) -> Result<(Vec<f32>, EncodeVisualization), String> {
+    encode_audio_samples_with_scheme(options.mode.scheme(), samples, sample_rate, message, options)
+}
+
+/// Encoder generic over the [`WatermarkScheme`] (`options.mode` is ignored)
+pub fn encode_audio_samples_with_scheme<S: WatermarkScheme + ?Sized>(
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    message: &str,
+    options: &EncodeOptions,
+) -> Result<(Vec<f32>, EncodeVisualization), String> {
+    let mut engine = Engine::new();
+    encode_with_engine(&mut engine, scheme, samples, sample_rate, message, options)
+}
 
-    // Build the bit sequence (pilot + length + message)
-    let bits = build_bit_sequence(message);
+/// Encoder body shared by the free functions and [`Engine`]
+pub(crate) fn encode_with_engine<S: WatermarkScheme + ?Sized>(
+    engine: &mut Engine,
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    message: &str,
+    options: &EncodeOptions,
+) -> Result<(Vec<f32>, EncodeVisualization), String> {
     // Calculate frame length
-    let frame_len = frame_length_samples(sample_rate, frame_duration_ms);
+    let frame_len = frame_length_samples(sample_rate, options.frame_duration_ms);
+
+    // Build the bit sequence (pilot + header + message), split over frames if asked to
+    let spectrum_len = frame_len.next_power_of_two().max(2) / 2 + 1;
+    let chunk_bits = segment::chunk_bits_with_pilot(scheme.capacity(spectrum_len), options.pilot.length());
+    let segments = match (options.layout, chunk_bits) {
+        (PayloadLayout::Segmented, Some(chunk_bits)) => build_segment_sequences(
+            message,
+            scheme.id(),
+            options.frame_duration_ms,
+            chunk_bits,
+            PayloadCoding::from_options(options),
+        ),
+        _ => None,
+    };
+    // Messages too long to segment go out whole, in the repeated (or legacy) layout
+    let stream = match segments {
+        Some(segments) => FrameBits::Segments(segments),
+        None => FrameBits::Whole(build_frame_bit_sequence(
+            message,
+            scheme.id(),
+            options.frame_duration_ms,
+            PayloadCoding::from_options(options),
+            scheme.capacity(spectrum_len),
+        )?),
+    };
+    embed_stream(engine, scheme, samples, sample_rate, &stream, options)
+}
+
+/// Embed prepared per-frame bits (`options.layout` has already been applied)
+/// Errors if `options.interleaver` is invalid (see [`Interleaver::check`]).
+pub(crate) fn embed_stream<S: WatermarkScheme + ?Sized>(
+    engine: &mut Engine,
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    stream: &FrameBits,
+    options: &EncodeOptions,
+) -> Result<(Vec<f32>, EncodeVisualization), String> {
+    let frame_len = frame_length_samples(sample_rate, options.frame_duration_ms);
+    let bits = stream.for_frame(0).to_vec();
+
+    // Permute every frame's bits onto the bins (the visualisation keeps stream order)
+    let spectrum_len = frame_len.next_power_of_two().max(2) / 2 + 1;
+    let interleaved = options
+        .interleaver
+        .frame_order(scheme.capacity(spectrum_len), options.pilot.length())?
+        .map(|order| stream.map(&|bits| interleave::interleave(bits, &order)));
+    let stream = interleaved.as_ref().unwrap_or(stream);
+
     if frame_len <= START_BIN {
         // Return original samples if frame length is too small
         let empty_viz = EncodeVisualization {
@@ -77,19 +280,27 @@ pub fn encode_audio_samples_with_viz(
             watermarked_frame: Vec::new(),
             bit_sequence: bits,
         };
-        return (samples.to_vec(), empty_viz);
+        return Ok((samples.to_vec(), empty_viz));
     }
 
     // Extract first frame for visualization
     let first_frame_original: Vec<f32> = samples.iter().take(frame_len).copied().collect();
 
     // Convert strength percentage to fraction
-    // Keep subtle: map 15% → 0.75, cap at 0.6
-    let strength = (strength_percent as f32 / 20.0).min(0.6);
+    let strength = strength_from_percent(options.strength_percent);
 
     // Embed watermark into audio via FFT processing
-    let encoded = embed_watermark_fft(samples, &bits, frame_len, strength);
-    
+    let params = EmbedParams {
+        scheme,
+        sample_rate,
+        frame_len,
+        stream,
+        strength,
+        strength_percent: options.strength_percent,
+        gate: options.energy_gate.as_ref(),
+    };
+    let encoded = embed_frames(engine, samples, &params);
+
     // Extract first frame of watermarked audio for visualization
     let first_frame_watermarked: Vec<f32> = encoded.iter().take(frame_len).copied().collect();
 
@@ -99,15 +310,20 @@ pub fn encode_audio_samples_with_viz(
         bit_sequence: bits,
     };
 
-    (encoded, viz)
+    Ok((encoded, viz))
 }
 
 pub fn encode_sample(message: &str) {
+    encode_sample_with_scheme(message, &MultiplicativeScheme);
+}
+
+/// [`encode_sample`] with any [`WatermarkScheme`] (used by the CLI's `--scheme`)
+pub fn encode_sample_with_scheme<S: WatermarkScheme + ?Sized>(message: &str, scheme: &S) {
     // Step 1: Load audio and get normalized samples + metadata
     let (base_samples, base_spec) = load_and_normalize_audio(Path::new(INPUT_PATH));
 
-    // Step 2: Build the bit sequence (pilot + length + message)
-    let bits = build_bit_sequence(message);
+    // One engine for the whole grid so each FFT size is planned once
+    let mut engine = Engine::new();
 
     // Step 3: Iterate through experiment grid and emit each combination
     for &target_rate in SAMPLE_RATES.iter() {
@@ -121,7 +337,7 @@ pub fn encode_sample(message: &str) {
             ))
         };
 
-        let mut spec_for_rate = base_spec.clone();
+        let mut spec_for_rate = base_spec;
         spec_for_rate.sample_rate = target_rate;
 
         for &frame_ms in FRAME_DURATIONS_MS.iter() {
@@ -134,30 +350,52 @@ pub fn encode_sample(message: &str) {
                 continue;
             }
 
+            // Step 2: Build the bit sequence (pilot + header + message); the header records frame_ms
+            let capacity = scheme.capacity(frame_len.next_power_of_two().max(2) / 2 + 1);
+            let stream = FrameBits::Whole(
+                build_frame_bit_sequence(message, scheme.id(), frame_ms, PayloadCoding::default(), capacity)
+                    .expect("unkeyed messages always encode"),
+            );
+
             for &strength_percent in WATERMARK_STRENGTHS.iter() {
                 let strength = (strength_percent.max(15) as f32 / 15.0).min(1.0);
 
                 // Step 3: Embed bits into audio via FFT processing
-                let encoded =
-                    embed_watermark_fft(samples_for_rate.as_ref(), &bits, frame_len, strength);
+                let params = EmbedParams {
+                    scheme,
+                    sample_rate: target_rate,
+                    frame_len,
+                    stream: &stream,
+                    strength,
+                    strength_percent,
+                    gate: None,
+                };
+                let encoded = embed_frames(&mut engine, samples_for_rate.as_ref(), &params);
 
                 // Step 4: Convert back to i16 samples
                 let quantized = quantize_to_i16(encoded);
 
                 // Step 5: Write the watermarked audio to disk
                 let output_path = experiment_output_path(target_rate, frame_ms, strength_percent);
-                write_wav_file(&output_path, &quantized, spec_for_rate.clone());
+                write_wav_file(&output_path, &quantized, spec_for_rate);
 
                 if target_rate == base_spec.sample_rate && frame_ms == 32 && strength_percent == 15
                 {
                     // Maintain legacy output for decoder convenience
-                    write_wav_file(Path::new(OUTPUT_PATH), &quantized, spec_for_rate.clone());
+                    write_wav_file(Path::new(OUTPUT_PATH), &quantized, spec_for_rate);
                 }
             }
         }
     }
 }
 
+/// Map a strength percentage to the bin scaling fraction.
+/// Percent is floored at 15 so the watermark survives noisy audio, and the
+/// fraction is capped at 0.6 to keep it subtle (15% → 0.75 → 0.6).
+pub fn strength_from_percent(strength_percent: u32) -> f32 {
+    (strength_percent.max(15) as f32 / 20.0).min(0.6)
+}
+
 // =============================================================================
 // STEP 1: Load and normalize audio
 // =============================================================================
@@ -189,17 +427,248 @@ fn load_and_normalize_audio(input_path: &Path) -> (Vec<f32>, hound::WavSpec) {
 }
 
 // =============================================================================
-// STEP 2: Build bit sequence (pilot + length + message)
+// STEP 2: Build bit sequence (pilot + header + message)
 // =============================================================================
 
-fn build_bit_sequence(message: &str) -> Vec<u8> {
+/// Bitstream for `message` with a header naming the default scheme and 32 ms frames.
+pub fn build_bit_sequence(message: &str) -> Vec<u8> {
+    build_bit_sequence_for(message, MultiplicativeScheme.id(), 32)
+}
+
+/// Pilot, version 1 header (see [`crate::header`]) and payload.
+/// Messages longer than [`MAX_PAYLOAD_BYTES`] fall back to the legacy layout.
+pub fn build_bit_sequence_for(message: &str, scheme_id: &str, frame_duration_ms: u32) -> Vec<u8> {
+    build_coded_bit_sequence(message, scheme_id, frame_duration_ms, PayloadCoding::default())
+        .expect("unkeyed messages fall back to the legacy layout")
+}
+
+/// [`build_bit_sequence_for`] with the message packed, encrypted and/or tagged as `coding` asks.
+/// A plain payload too long for a version 1 header falls back to the legacy layout; with packing, FEC or
+/// a key it is an error instead, as a legacy stream can carry none of them (see [`check_message`]).
+pub fn build_coded_bit_sequence(
+    message: &str,
+    scheme_id: &str,
+    frame_duration_ms: u32,
+    coding: PayloadCoding,
+) -> Result<Vec<u8>, String> {
+    let message_bytes = message.as_bytes();
+    let pilot = coding.pilot.bits()?;
+    let Some((header, payload)) = code_payload(message_bytes, Header::new(scheme_id, frame_duration_ms, 0), coding) else {
+        if coding.encryption.is_some() || coding.auth.is_some() {
+            return Err(too_long_to_key(message_bytes.len()));
+        }
+        if !coding.is_plain() {
+            return Err(too_long_to_code(message_bytes.len()));
+        }
+        return Ok(legacy_bits(message_bytes, &pilot));
+    };
+
+    let mut bits = Vec::new();
+
+    // 1. Pilot for frame detection and threshold calibration
+    bits.extend(pilot);
+
+    // 2. Header: format version, scheme, frame size, payload length, CRC
+    bits.extend(header.to_bits());
+
+    // 3. Message payload (8 bits per byte, MSB first), packed, sealed, tagged and FEC-coded if asked
+    push_payload(&mut bits, &payload, coding.fec);
+
+    println!(
+        "Encoding a {}-byte message ({} payload bytes, flags {:#06b})", // lengths only: the message may be private
+        message_bytes.len(),
+        header.length,
+        header.flags
+    );
+    println!(
+        "Total bits to embed (pilot + header + data): {}",
+        bits.len()
+    );
+
+    Ok(bits)
+}
+
+/// [`build_coded_bit_sequence`] for frames carrying `capacity` bits each. A plain version 1 stream the
+/// frame would cut off goes out in the legacy layout instead, whose 16-bit length field leaves the payload
+/// the bins it had before headers. Packing, FEC and keys have no legacy form, so a stream using any of them
+/// that does not fit is an error. Cycling schemes spread the stream over frames, so never need to fit.
+pub fn build_frame_bit_sequence(
+    message: &str,
+    scheme_id: &str,
+    frame_duration_ms: u32,
+    coding: PayloadCoding,
+    capacity: usize,
+) -> Result<Vec<u8>, String> {
+    let bits = build_coded_bit_sequence(message, scheme_id, frame_duration_ms, coding)?;
+    let cycling = capacity < coding.pilot.length().saturating_add(LENGTH_HEADER_BITS);
+    if bits.len() <= capacity || cycling {
+        return Ok(bits);
+    }
+    if !coding.is_plain() {
+        return Err(format!(
+            "the coded stream needs {} bits but a frame carries {capacity}: use longer frames, the segmented layout, or less coding",
+            bits.len()
+        ));
+    }
+    Ok(legacy_bits(message.as_bytes(), &coding.pilot.bits()?))
+}
+
+/// Per-frame bitstreams for the segmented layout: header and payload split into
+/// `chunk_bits` chunks, each behind a pilot and segment field (see [`crate::segment`]).
+/// `None` if the payload is too long for the header or needs more than [`segment::MAX_SEGMENTS`] chunks.
+pub fn build_segment_sequences(
+    message: &str,
+    scheme_id: &str,
+    frame_duration_ms: u32,
+    chunk_bits: usize,
+    coding: PayloadCoding,
+) -> Option<Vec<Vec<u8>>> {
     let message_bytes = message.as_bytes();
+
+    // Header (flagged as segmented) and payload form one stream to split
+    let mut header = Header::new(scheme_id, frame_duration_ms, 0);
+    header.flags |= FLAG_SEGMENTED;
+    let (header, payload) = code_payload(message_bytes, header, coding)?;
+    let mut stream = header.to_bits();
+    push_payload(&mut stream, &payload, coding.fec);
+    if stream.len().div_ceil(chunk_bits) > segment::MAX_SEGMENTS {
+        return None;
+    }
+
+    let segments = segment::split_stream_with_pilot(&stream, &coding.pilot.bits().ok()?, chunk_bits);
+    println!(
+        "Encoding a {}-byte message ({} payload bytes, flags {:#06b})", // lengths only: the message may be private
+        message_bytes.len(),
+        header.length,
+        header.flags
+    );
+    println!(
+        "Segments to embed: {} of up to {} data bits each",
+        segments.len(),
+        chunk_bits
+    );
+
+    Some(segments)
+}
+
+/// How a version 1 stream is framed, and its payload packed and protected (applied in field order after the pilot).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PayloadCoding<'a> {
+    pub packing: Packing,             // fewer bits per character (see [`crate::packing`])
+    pub encryption: Option<&'a [u8]>, // seal with ChaCha20-Poly1305 (see [`crate::cipher`])
+    pub auth: Option<&'a [u8]>,       // append an HMAC tag (see [`crate::auth`])
+    pub fec: Fec,                     // convolutional code over the payload bits (see [`crate::fec`])
+    pub pilot: Pilot,                 // sync sequence each frame starts with (see [`crate::pilot`])
+}
+
+impl<'a> PayloadCoding<'a> {
+    /// The pilot, packing, code and keys set in `options`.
+    pub fn from_options(options: &'a EncodeOptions) -> Self {
+        Self {
+            packing: options.packing,
+            encryption: options.encryption_key.as_deref(),
+            auth: options.auth_key.as_deref(),
+            fec: options.fec,
+            pilot: options.pilot,
+        }
+    }
+
+    /// No packing, FEC or keys: the only payloads the legacy layout can carry.
+    pub fn is_plain(&self) -> bool {
+        self.packing == Packing::None && self.fec == Fec::None && self.encryption.is_none() && self.auth.is_none()
+    }
+}
+
+/// `Err` with the reason if `message` cannot be embedded with `options`: a packed, FEC-coded, sealed or
+/// tagged payload must fit a version 1 header (plain messages fall back to the legacy layout).
+pub fn check_message(message: &str, options: &EncodeOptions) -> Result<(), String> {
+    let coding = PayloadCoding::from_options(options);
+    let header = Header::new(MultiplicativeScheme.id(), options.frame_duration_ms, 0); // only the length matters
+    if coding.is_plain() || code_payload(message.as_bytes(), header, coding).is_some() {
+        return Ok(());
+    }
+    if coding.encryption.is_some() || coding.auth.is_some() {
+        return Err(too_long_to_key(message.len()));
+    }
+    Err(too_long_to_code(message.len()))
+}
+
+fn too_long_to_key(message_len: usize) -> String {
+    format!(
+        "a {message_len}-byte message is too long to encrypt or authenticate: payloads are limited to {MAX_PAYLOAD_BYTES} bytes with overhead"
+    )
+}
+
+fn too_long_to_code(message_len: usize) -> String {
+    format!(
+        "a {message_len}-byte message is too long to pack or FEC-code: payloads are limited to {MAX_PAYLOAD_BYTES} bytes"
+    )
+}
+
+/// Final header (length and flags filled in) and payload: the message packed, sealed, then tagged as asked.
+/// `None` if the payload would be longer than a header can count.
+fn code_payload(message_bytes: &[u8], mut header: Header, coding: PayloadCoding) -> Option<(Header, Vec<u8>)> {
+    let packed = packing::pack(message_bytes, coding.packing);
+    if packed.is_some() {
+        header.flags |= FLAG_PACKED;
+    }
+    let message_bytes = packed.as_deref().unwrap_or(message_bytes);
+
+    header.length = message_bytes.len();
+    header.fec = coding.fec.code();
+    if coding.encryption.is_some() {
+        header.length += cipher::SEAL_BYTES;
+        header.flags |= FLAG_ENCRYPTED;
+    }
+    if coding.auth.is_some() {
+        header.length += auth::TAG_BYTES;
+        header.flags |= FLAG_AUTHENTICATED;
+    }
+    if header.length > MAX_PAYLOAD_BYTES {
+        return None;
+    }
+
+    // The cipher binds the final header, so it is sealed only once the header is complete
+    let mut payload = match coding.encryption {
+        Some(key) => cipher::seal(key, &header, message_bytes),
+        None => message_bytes.to_vec(),
+    };
+    if let Some(key) = coding.auth {
+        let tag = auth::tag(key, &payload);
+        payload.extend(tag);
+    }
+    Some((header, payload))
+}
+
+/// Append the payload's bits, through the convolutional code if `fec` asks for one.
+fn push_payload(bits: &mut Vec<u8>, payload: &[u8], fec: Fec) {
+    let mut payload_bits = Vec::with_capacity(payload.len() * 8);
+    push_bytes(&mut payload_bits, payload);
+    bits.extend(fec::encode(&payload_bits, fec));
+}
+
+/// Append bytes as bits, MSB first.
+fn push_bytes(bits: &mut Vec<u8>, bytes: &[u8]) {
+    for &byte in bytes {
+        for shift in (0..8).rev() {
+            bits.push((byte >> shift) & 1);
+        }
+    }
+}
+
+/// Version 0 bitstream: pilot, bare 16-bit length, payload (still decoded for old files).
+pub fn build_legacy_bit_sequence(message: &str) -> Vec<u8> {
+    legacy_bits(message.as_bytes(), &PILOT_PATTERN)
+}
+
+/// Version 0 bitstream behind `pilot`.
+fn legacy_bits(message_bytes: &[u8], pilot: &[u8]) -> Vec<u8> {
     let length_header = message_bytes.len() as u16;
 
     let mut bits = Vec::new();
 
     // 1. Pilot pattern for threshold calibration
-    bits.extend_from_slice(&PILOT_PATTERN);
+    bits.extend_from_slice(pilot);
 
     // 2. Length header (16 bits, MSB first)
     for shift in (0..16).rev() {
@@ -216,11 +685,7 @@ fn build_bit_sequence(message: &str) -> Vec<u8> {
         }
     }
 
-    println!(
-        "Encoding message {:?} ({} bytes)",
-        message,
-        message_bytes.len()
-    );
+    println!("Encoding a {}-byte message", message_bytes.len());
     println!(
         "Total bits to embed (pilot + length + data): {}",
         bits.len()
@@ -233,63 +698,218 @@ fn build_bit_sequence(message: &str) -> Vec<u8> {
 // STEP 3: Embed watermark using FFT
 // =============================================================================
 
-fn embed_watermark_fft(audio: &[f32], bits: &[u8], frame_len: usize, strength: f32) -> Vec<f32> {
+/// Bits handed to each frame
+pub(crate) enum FrameBits {
+    /// The same bitstream in every frame
+    Whole(Vec<u8>),
+    /// Frame `i` carries segment `i mod len`
+    Segments(Vec<Vec<u8>>),
+    /// `(first frame, bits)` per block, in frame order; each block counts frames from its own start,
+    /// and the first block also covers any frames before it (see [`crate::timeline`])
+    Blocks(Vec<(usize, FrameBits)>),
+}
+
+impl FrameBits {
+    pub(crate) fn for_frame(&self, frame_index: usize) -> &[u8] {
+        match self {
+            FrameBits::Whole(bits) => bits,
+            FrameBits::Segments(segments) => &segments[frame_index % segments.len()],
+            FrameBits::Blocks(blocks) => {
+                let block = blocks.partition_point(|(first, _)| *first <= frame_index).saturating_sub(1);
+                let (first, bits) = &blocks[block];
+                bits.for_frame(frame_index.saturating_sub(*first))
+            }
+        }
+    }
+
+    /// The same layout with `f` applied to every bitstream.
+    pub(crate) fn map(&self, f: &impl Fn(&[u8]) -> Vec<u8>) -> FrameBits {
+        match self {
+            FrameBits::Whole(bits) => FrameBits::Whole(f(bits)),
+            FrameBits::Segments(segments) => FrameBits::Segments(segments.iter().map(|bits| f(bits)).collect()),
+            FrameBits::Blocks(blocks) => {
+                FrameBits::Blocks(blocks.iter().map(|(first, bits)| (*first, bits.map(f))).collect())
+            }
+        }
+    }
+}
+
+/// Per-call embedding settings resolved from [`EncodeOptions`]
+struct EmbedParams<'a, S: ?Sized> {
+    scheme: &'a S,
+    sample_rate: u32,
+    frame_len: usize,
+    stream: &'a FrameBits,
+    strength: f32,
+    strength_percent: u32,
+    gate: Option<&'a EnergyGate>,
+}
+
+pub fn embed_watermark_fft(
+    engine: &mut Engine,
+    audio: &[f32],
+    bits: &[u8],
+    frame_len: usize,
+    strength: f32,
+) -> Vec<f32> {
+    let stream = FrameBits::Whole(bits.to_vec());
+    let params = EmbedParams {
+        scheme: &MultiplicativeScheme,
+        sample_rate: 0, // unused by the multiplicative scheme
+        frame_len,
+        stream: &stream,
+        strength,
+        strength_percent: DEFAULT_STRENGTH_PERCENT, // unused by the multiplicative scheme
+        gate: None,
+    };
+    embed_frames(engine, audio, &params)
+}
+
+fn embed_frames<S: WatermarkScheme + ?Sized>(
+    engine: &mut Engine,
+    audio: &[f32],
+    params: &EmbedParams<S>,
+) -> Vec<f32> {
+    let frame_len = params.frame_len;
+
     // Use next_power_of_two to match decoder's FFT size
     let fft_len = frame_len.next_power_of_two().max(2);
-    
-    let mut planner = RealFftPlanner::<f32>::new();
-    let fft = planner.plan_fft_forward(fft_len);
-    let ifft = planner.plan_fft_inverse(fft_len);
 
-    let mut buffer = vec![0.0f32; fft_len];
+    // Plans and buffers come from the engine, so nothing is allocated per frame
+    let ctx = engine.context(fft_len);
 
     //buffer (256 slots):
     //[___|___|___|___|___| ... |___|___|___]
 
-    let mut spectrum = fft.make_output_vec();
-    let mut output = Vec::new();
-
-    if START_BIN >= spectrum.len() {
+    if START_BIN >= ctx.buffers.spectrum.len() {
         return audio.to_vec();
     }
 
-    // Process each frame
-    for chunk in audio.chunks(frame_len) {
-        // Load audio
-        buffer.fill(0.0); // wipe clean every time because multiple iterations
-        buffer[..chunk.len()].copy_from_slice(chunk); //copies chunk into our empty slots
-
-        // Time → Frequency
-        fft.process(&mut buffer, &mut spectrum).expect("FFT failed"); //i will explain in the decoder video
-
-        // Compute masking reference: max magnitude within watermark bins
-        let max_mag = spectrum[START_BIN..]
-            .iter()
-            .map(|c| c.norm())
-            .fold(1e-6f32, f32::max);
-
-        // Embed bits with simple scaling in watermark bins
-        for (&bit, bin) in bits.iter().zip(&mut spectrum[START_BIN..]) {
-            let scale = if bit == 1 {
-                1.0 + strength
-            } else {
-                (1.0 - strength).max(0.0)
-            };
-            bin.re *= scale;
-            bin.im *= scale;
-        }
+    let mut output = vec![0.0f32; audio.len()];
+
+    // Process each frame (frames are independent, so this can fan out across threads)
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    {
+        use rayon::prelude::*;
 
-        // Frequency → Time
-        ifft.process(&mut spectrum, &mut buffer)
-            .expect("IFFT failed");
+        let plans = &ctx.plans;
+        output
+            .par_chunks_mut(frame_len)
+            .enumerate()
+            .for_each_init(
+                || FrameBuffers::new(plans),
+                |buffers, (index, out)| embed_gated_frame(plans, buffers, index, audio, params, out),
+            );
+    }
 
-        // Normalize and append
-        output.extend(buffer[..chunk.len()].iter().map(|x| x / fft_len as f32));
+    #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+    for (index, out) in output.chunks_mut(frame_len).enumerate() {
+        embed_gated_frame(&ctx.plans, &mut ctx.buffers, index, audio, params, out);
     }
 
     output
 }
 
+/// Route one frame through the energy gate before embedding
+fn embed_gated_frame<S: WatermarkScheme + ?Sized>(
+    plans: &FftPlans,
+    buffers: &mut FrameBuffers,
+    frame_index: usize,
+    audio: &[f32],
+    params: &EmbedParams<S>,
+    out: &mut [f32],
+) {
+    let start = frame_index * params.frame_len;
+    let chunk = &audio[start..start + out.len()];
+    let carrier_rms = match params.gate {
+        Some(gate) if gate.is_quiet(chunk) => match gate.policy {
+            QuietFramePolicy::Embed => None,
+            QuietFramePolicy::Skip => {
+                out.copy_from_slice(chunk); // nothing to hide in, pass through
+                return;
+            }
+            QuietFramePolicy::Inject => Some(gate.carrier_rms()),
+        },
+        _ => None,
+    };
+    embed_frame(plans, buffers, frame_index, audio, params, carrier_rms, out);
+}
+
+fn embed_frame<S: WatermarkScheme + ?Sized>(
+    plans: &FftPlans,
+    buffers: &mut FrameBuffers,
+    frame_index: usize,
+    audio: &[f32],
+    params: &EmbedParams<S>,
+    carrier_rms: Option<f32>,
+    out: &mut [f32],
+) {
+    let fft_len = buffers.time.len();
+    let start = frame_index * params.frame_len;
+    let chunk = &audio[start..start + out.len()];
+    let domain = params.scheme.domain();
+    let bits = params.stream.for_frame(frame_index);
+
+    // Load audio (zero-padded) and go Time → Frequency
+    if domain == Domain::Spectrum {
+        buffers.forward(plans, chunk); //i will explain in the decoder video
+    }
+
+    // Let the scheme write its bits
+    params.scheme.embed_frame(&mut EmbedFrame {
+        index: frame_index,
+        sample_rate: params.sample_rate,
+        bits,
+        strength: params.strength,
+        strength_percent: params.strength_percent,
+        spectrum: &mut buffers.spectrum,
+        scratch: &mut buffers.mask,
+        host: audio,
+        start,
+        samples: out,
+    });
+    if domain == Domain::Time {
+        return; // the scheme wrote `out` itself
+    }
+
+    // Quiet frame: add a carrier to the "1" bins so there is something to scale
+    if let Some(rms) = carrier_rms {
+        inject_carrier(&mut buffers.spectrum, frame_index, bits, rms);
+    }
+
+    // Frequency → Time
+    buffers.inverse(plans);
+
+    // Normalize into the output slot
+    for (dst, &x) in out.iter_mut().zip(&buffers.time[..chunk.len()]) {
+        *dst = x / fft_len as f32;
+    }
+}
+
+/// Add a fixed-magnitude, pseudo-random-phase component to every "1" bin so the
+/// carrier alone reaches `rms` (linear, after the 1/N inverse normalisation).
+fn inject_carrier(spectrum: &mut [Complex<f32>], frame_index: usize, bits: &[u8], rms: f32) {
+    let fft_len = (spectrum.len() - 1) * 2;
+    let ones = bits
+        .iter()
+        .take(spectrum.len() - START_BIN)
+        .filter(|&&bit| bit == 1)
+        .count();
+    if ones == 0 {
+        return;
+    }
+
+    // Each bin k contributes a sinusoid of amplitude 2|X_k|/N, i.e. RMS sqrt(2)|X_k|/N
+    let magnitude = rms * fft_len as f32 / (2.0 * ones as f32).sqrt();
+    for (offset, (&bit, bin)) in bits.iter().zip(&mut spectrum[START_BIN..]).enumerate() {
+        if bit == 1 {
+            let phase = energy::carrier_phase(frame_index, START_BIN + offset);
+            *bin += Complex::from_polar(magnitude, phase);
+        }
+    }
+    scheme::real_nyquist(spectrum);
+}
+
 // =============================================================================
 // STEP 4: Quantize to i16 samples
 // =============================================================================
@@ -331,7 +951,7 @@ fn experiment_output_path(sample_rate: u32, frame_ms: u32, strength_percent: u32
         .join(format!("{sample_rate}_{frame_ms}_{strength_percent}.wav"))
 }
 
-fn frame_length_samples(sample_rate: u32, frame_ms: u32) -> usize {
+pub fn frame_length_samples(sample_rate: u32, frame_ms: u32) -> usize {
     (((sample_rate as f32) * (frame_ms as f32) / 1000.0).round() as usize).max(1)
 }
 
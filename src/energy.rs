@@ -0,0 +1,78 @@
+// =============================================================================
+// Energy gate - decide which frames are loud enough to carry the watermark
+// =============================================================================
+
+/// Default gate level: frames quieter than this are treated as silence.
+pub const DEFAULT_GATE_DB: f32 = -60.0;
+
+/// How far above the gate an injected carrier sits, so the decoder's gate lets it through.
+const CARRIER_HEADROOM_DB: f32 = 6.0;
+
+/// What the encoder does with a frame below the gate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuietFramePolicy {
+    /// Scale bins as usual (no-op on digital silence).
+    #[default]
+    Embed,
+    /// Leave the frame untouched.
+    Skip,
+    /// Add a low-level carrier in the "1" bins so the frame still carries data.
+    Inject,
+}
+
+/// Frame energy gate shared by the encoder and the decoder.
+///
+/// The encoder applies `policy` to quiet frames; the decoder ignores frames
+/// that are still below `threshold_db` after encoding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnergyGate {
+    pub threshold_db: f32, // frame RMS in dBFS
+    pub policy: QuietFramePolicy,
+}
+
+impl EnergyGate {
+    pub fn new(policy: QuietFramePolicy) -> Self {
+        Self {
+            threshold_db: DEFAULT_GATE_DB,
+            policy,
+        }
+    }
+
+    pub fn is_quiet(&self, frame: &[f32]) -> bool {
+        frame_rms_db(frame) < self.threshold_db
+    }
+
+    /// Target RMS (linear) for an injected carrier.
+    pub fn carrier_rms(&self) -> f32 {
+        db_to_amplitude(self.threshold_db + CARRIER_HEADROOM_DB)
+    }
+}
+
+/// RMS level of a frame in dBFS (full scale = 1.0). Empty or silent frames give -inf.
+pub fn frame_rms_db(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let mean_square = frame.iter().map(|x| x * x).sum::<f32>() / frame.len() as f32;
+    10.0 * mean_square.log10()
+}
+
+pub fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Deterministic carrier phase for a (frame, bin) pair, so serial and parallel
+/// encodes match and no RNG state has to be threaded through.
+pub fn carrier_phase(frame_index: usize, bin: usize) -> f32 {
+    let x = hash64((frame_index as u64) << 32 | bin as u64);
+    (x >> 40) as f32 / (1u64 << 24) as f32 * std::f32::consts::TAU
+}
+
+/// MurmurHash3's 64-bit finalizer: a cheap, well-mixed hash of `x`.
+pub fn hash64(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^ (x >> 33)
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+
+use crate::decoder::{self, DecodeOptions, DecodeVisualization, DecodedWatermark};
+use crate::encoder::{self, EncodeOptions, EncodeVisualization};
+use crate::integrity::{self, IntegrityReport};
+use crate::scheme::WatermarkScheme;
+use crate::timeline::{self, TimelineEntry};
+
+// =============================================================================
+// Engine - reusable FFT plans and scratch buffers
+// =============================================================================
+
+/// Reusable encoding/decoding context.
+///
+/// Holds one set of FFT plans and frame buffers per FFT size, so repeated
+/// calls (batch servers, WASM sessions) skip planning and per-frame allocation.
+pub struct Engine {
+    planner: RealFftPlanner<f32>,
+    contexts: HashMap<usize, FftContext>,
+}
+
+/// Forward and inverse plans for one FFT size (cheap to clone, thread-safe).
+#[derive(Clone)]
+pub struct FftPlans {
+    pub forward: Arc<dyn RealToComplex<f32>>,
+    pub inverse: Arc<dyn ComplexToReal<f32>>,
+}
+
+/// Working memory for processing a single frame.
+pub(crate) struct FrameBuffers {
+    pub time: Vec<f32>,                      // zero-padded time-domain frame
+    pub spectrum: Vec<Complex<f32>>,         // half spectrum
+    pub forward_scratch: Vec<Complex<f32>>,  // realfft forward scratch
+    pub inverse_scratch: Vec<Complex<f32>>,  // realfft inverse scratch
+    pub magnitudes: Vec<f32>,                // watermark-bin magnitudes
+    pub mask: Vec<f32>,                      // additive mode: masking threshold per bin
+    pub log_mags: Vec<f32>,                  // spectral_scores: log spectrum
+    pub prefix: Vec<f64>,                    // spectral_scores: prefix sums
+    pub scores: Vec<f32>,                    // spectral_scores: output
+    pub reordered: Vec<f32>,                 // scores put back in stream order (interleaving)
+}
+
+/// Plans plus buffers for one FFT size.
+pub(crate) struct FftContext {
+    pub plans: FftPlans,
+    pub buffers: FrameBuffers,
+}
+
+impl FrameBuffers {
+    pub fn new(plans: &FftPlans) -> Self {
+        let fft_len = plans.forward.len();
+        let spectrum = plans.forward.make_output_vec();
+        let bins = spectrum.len();
+        Self {
+            time: vec![0.0; fft_len],
+            spectrum,
+            forward_scratch: plans.forward.make_scratch_vec(),
+            inverse_scratch: plans.inverse.make_scratch_vec(),
+            magnitudes: Vec::with_capacity(bins),
+            mask: Vec::with_capacity(bins),
+            log_mags: Vec::with_capacity(bins),
+            prefix: Vec::with_capacity(bins + 1),
+            scores: Vec::with_capacity(bins),
+            reordered: Vec::with_capacity(bins),
+        }
+    }
+
+    /// Zero-pad `frame` into the time buffer and run the forward FFT.
+    pub fn forward(&mut self, plans: &FftPlans, frame: &[f32]) {
+        self.time.fill(0.0);
+        self.time[..frame.len()].copy_from_slice(frame);
+        plans
+            .forward
+            .process_with_scratch(&mut self.time, &mut self.spectrum, &mut self.forward_scratch)
+            .expect("FFT failed");
+    }
+
+    /// [`FrameBuffers::forward`] with a Hann window over `frame` first.
+    pub fn forward_hann(&mut self, plans: &FftPlans, frame: &[f32]) {
+        self.time.fill(0.0);
+        let len = frame.len() as f32;
+        for (n, (dst, &x)) in self.time.iter_mut().zip(frame).enumerate() {
+            *dst = x * (0.5 - 0.5 * (std::f32::consts::TAU * n as f32 / len).cos());
+        }
+        plans
+            .forward
+            .process_with_scratch(&mut self.time, &mut self.spectrum, &mut self.forward_scratch)
+            .expect("FFT failed");
+    }
+
+    /// Run the inverse FFT of the spectrum back into the time buffer (unnormalised).
+    pub fn inverse(&mut self, plans: &FftPlans) {
+        plans
+            .inverse
+            .process_with_scratch(&mut self.spectrum, &mut self.time, &mut self.inverse_scratch)
+            .expect("IFFT failed");
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self {
+            planner: RealFftPlanner::new(),
+            contexts: HashMap::new(),
+        }
+    }
+
+    /// Plans and buffers for `fft_len`, created on first use.
+    pub(crate) fn context(&mut self, fft_len: usize) -> &mut FftContext {
+        let planner = &mut self.planner;
+        self.contexts.entry(fft_len).or_insert_with(|| {
+            let plans = FftPlans {
+                forward: planner.plan_fft_forward(fft_len),
+                inverse: planner.plan_fft_inverse(fft_len),
+            };
+            let buffers = FrameBuffers::new(&plans);
+            FftContext { plans, buffers }
+        })
+    }
+
+    /// Same as [`encoder::encode_audio_samples`], reusing this engine's plans.
+    pub fn encode_audio_samples(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+        message: &str,
+        frame_duration_ms: u32,
+        strength_percent: u32,
+    ) -> Vec<f32> {
+        let (encoded, _) = self.encode_audio_samples_with_viz(
+            samples,
+            sample_rate,
+            message,
+            frame_duration_ms,
+            strength_percent,
+        );
+        encoded
+    }
+
+    /// Same as [`encoder::encode_audio_samples_with_viz`], reusing this engine's plans.
+    pub fn encode_audio_samples_with_viz(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+        message: &str,
+        frame_duration_ms: u32,
+        strength_percent: u32,
+    ) -> (Vec<f32>, EncodeVisualization) {
+        let options = EncodeOptions::new(frame_duration_ms, strength_percent);
+        self.encode_with_options(samples, sample_rate, message, &options).expect("unkeyed messages always encode")
+    }
+
+    /// Same as [`encoder::encode_audio_samples_with_options`], reusing this engine's plans.
+    pub fn encode_with_options(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+        message: &str,
+        options: &EncodeOptions,
+    ) -> Result<(Vec<f32>, EncodeVisualization), String> {
+        self.encode_with_scheme(options.mode.scheme(), samples, sample_rate, message, options)
+    }
+
+    /// Same as [`encoder::encode_audio_samples_with_scheme`], reusing this engine's plans.
+    pub fn encode_with_scheme<S: WatermarkScheme + ?Sized>(
+        &mut self,
+        scheme: &S,
+        samples: &[f32],
+        sample_rate: u32,
+        message: &str,
+        options: &EncodeOptions,
+    ) -> Result<(Vec<f32>, EncodeVisualization), String> {
+        encoder::encode_with_engine(self, scheme, samples, sample_rate, message, options)
+    }
+
+    /// Same as [`decoder::decode_audio_samples`], reusing this engine's plans.
+    pub fn decode_audio_samples(&mut self, samples: &[f32], sample_rate: u32) -> DecodedWatermark {
+        let (decoded, _) = self.decode_audio_samples_with_viz(samples, sample_rate);
+        decoded
+    }
+
+    /// Same as [`decoder::decode_audio_samples_with_viz`], reusing this engine's plans.
+    pub fn decode_audio_samples_with_viz(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+    ) -> (DecodedWatermark, DecodeVisualization) {
+        self.decode_with_options(samples, sample_rate, &DecodeOptions::default())
+    }
+
+    /// Same as [`decoder::decode_audio_samples_with_options`], reusing this engine's plans.
+    pub fn decode_with_options(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+        options: &DecodeOptions,
+    ) -> (DecodedWatermark, DecodeVisualization) {
+        let options = decoder::configure(self, samples, sample_rate, options);
+        self.decode_with_scheme(options.scheme(), samples, sample_rate, &options)
+    }
+
+    /// Same as [`decoder::decode_audio_samples_with_scheme`], reusing this engine's plans.
+    pub fn decode_with_scheme<S: WatermarkScheme + ?Sized>(
+        &mut self,
+        scheme: &S,
+        samples: &[f32],
+        sample_rate: u32,
+        options: &DecodeOptions,
+    ) -> (DecodedWatermark, DecodeVisualization) {
+        decoder::decode_with_engine(self, scheme, samples, sample_rate, options)
+    }
+
+    /// Same as [`timeline::encode_timeline`], reusing this engine's plans.
+    pub fn encode_timeline(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+        payloads: &[(f32, String)],
+        options: &EncodeOptions,
+    ) -> Result<(Vec<f32>, EncodeVisualization), String> {
+        timeline::encode_with_engine(self, options.mode.scheme(), samples, sample_rate, payloads, options)
+    }
+
+    /// Same as [`timeline::encode_timeline_with`], reusing this engine's plans.
+    pub fn encode_timeline_with(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+        block_seconds: f32,
+        payload_at: impl FnMut(f32) -> String,
+        options: &EncodeOptions,
+    ) -> Result<(Vec<f32>, EncodeVisualization), String> {
+        let payloads = timeline::block_payloads(samples.len(), sample_rate, block_seconds, payload_at)?;
+        self.encode_timeline(samples, sample_rate, &payloads, options)
+    }
+
+    /// Same as [`timeline::decode_timeline`], reusing this engine's plans.
+    pub fn decode_timeline(&mut self, samples: &[f32], sample_rate: u32, options: &DecodeOptions) -> Vec<TimelineEntry> {
+        let options = decoder::configure(self, samples, sample_rate, options);
+        timeline::decode_with_engine(self, options.scheme(), samples, sample_rate, &options)
+    }
+
+    /// Same as [`integrity::integrity_map`], reusing this engine's plans.
+    pub fn integrity_map(&mut self, samples: &[f32], sample_rate: u32, options: &DecodeOptions) -> Result<IntegrityReport, String> {
+        let options = decoder::configure(self, samples, sample_rate, options);
+        self.integrity_map_with_scheme(options.scheme(), samples, sample_rate, &options)
+    }
+
+    /// Same as [`integrity::integrity_map_with_scheme`], reusing this engine's plans.
+    pub fn integrity_map_with_scheme<S: WatermarkScheme + ?Sized>(
+        &mut self,
+        scheme: &S,
+        samples: &[f32],
+        sample_rate: u32,
+        options: &DecodeOptions,
+    ) -> Result<IntegrityReport, String> {
+        integrity::check_with_engine(self, scheme, samples, sample_rate, options)
+    }
+}
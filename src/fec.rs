@@ -0,0 +1,162 @@
+use crate::header::Header;
+
+// =============================================================================
+// Forward error correction - K=7 convolutional code with soft Viterbi decoding
+// =============================================================================
+//
+// The payload bits are run through the rate-1/2, constraint-length-7 code
+// with the generators 171 and 133 (octal) used by 802.11 and DVB, followed by
+// six zero bits that bring the encoder back to state 0. Puncturing drops some
+// of the coded bits on a fixed pattern to trade robustness for capacity:
+//
+//   rate 1/2  every coded bit
+//   rate 2/3  per 2 data bits: A1 B1 A2      (B2 dropped)
+//   rate 3/4  per 3 data bits: A1 B1 A2 B3   (B2, A3 dropped)
+//
+// The coded bits are zero-padded to a whole byte, so byte-stepped searches
+// (the cycle fold, segment chunks) still line up. Only the payload is coded:
+// the header stays plain so the decoder can read the code from its FEC field
+// before anything else, and the CRC already guards it.
+//
+// The decoder runs Viterbi over the per-bit LLRs of `crate::decoder::bit_llrs`:
+// a dropped bit contributes nothing, and a confidently read bit outweighs a
+// doubtful one, which is where soft decisions beat voting on hard bits.
+
+/// Error-correcting code applied to the payload.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fec {
+    /// Payload bits embedded as they are
+    #[default]
+    None,
+    /// K=7 convolutional code at rate 1/2
+    Half,
+    /// The rate 1/2 code punctured to 2/3
+    TwoThirds,
+    /// The rate 1/2 code punctured to 3/4
+    ThreeQuarters,
+}
+
+const CONSTRAINT_LENGTH: usize = 7;
+const TAIL_BITS: usize = CONSTRAINT_LENGTH - 1;
+const STATES: usize = 1 << TAIL_BITS;
+const GENERATORS: [u32; 2] = [0o171, 0o133]; // tap bit 6 is the newest input bit
+
+impl Fec {
+    pub const ALL: [Fec; 4] = [Fec::None, Fec::Half, Fec::TwoThirds, Fec::ThreeQuarters];
+
+    /// Code for the header's 2-bit FEC field.
+    pub fn code(self) -> u8 {
+        Self::ALL.iter().position(|&fec| fec == self).unwrap_or(0) as u8
+    }
+
+    /// Code named by a header.
+    pub fn from_header(header: &Header) -> Self {
+        Self::ALL.get(usize::from(header.fec)).copied().unwrap_or(Fec::None)
+    }
+
+    /// Which of generator A's and B's bits are kept, per data bit of the puncturing period.
+    fn puncturing(self) -> &'static [[bool; 2]] {
+        match self {
+            Fec::None | Fec::Half => &[[true, true]],
+            Fec::TwoThirds => &[[true, true], [true, false]],
+            Fec::ThreeQuarters => &[[true, true], [true, false], [false, true]],
+        }
+    }
+}
+
+/// Embedded bits for a `payload_bytes`-byte payload under `fec`.
+pub fn coded_len(payload_bytes: usize, fec: Fec) -> usize {
+    let data_bits = payload_bytes * 8;
+    if fec == Fec::None {
+        return data_bits;
+    }
+    let pattern = fec.puncturing();
+    let kept: usize = (0..data_bits + TAIL_BITS)
+        .map(|bit| pattern[bit % pattern.len()].iter().filter(|&&keep| keep).count())
+        .sum();
+    kept.next_multiple_of(8)
+}
+
+/// Bits carried for the payload `header` describes.
+pub fn header_coded_len(header: &Header) -> usize {
+    coded_len(header.length, Fec::from_header(header))
+}
+
+/// Payload bits encoded, terminated, punctured and padded to [`coded_len`].
+pub fn encode(bits: &[u8], fec: Fec) -> Vec<u8> {
+    if fec == Fec::None {
+        return bits.to_vec();
+    }
+    let pattern = fec.puncturing();
+    let mut coded = Vec::with_capacity(bits.len() * 2 + TAIL_BITS * 2);
+    let mut state = 0usize;
+    let tail = std::iter::repeat_n(0u8, TAIL_BITS);
+    for (index, bit) in bits.iter().copied().chain(tail).enumerate() {
+        let register = usize::from(bit & 1) << TAIL_BITS | state;
+        for (&generator, &keep) in GENERATORS.iter().zip(&pattern[index % pattern.len()]) {
+            if keep {
+                coded.push(parity(register, generator));
+            }
+        }
+        state = register >> 1;
+    }
+    coded.resize(coded.len().next_multiple_of(8), 0); // byte padding
+    coded
+}
+
+/// Most likely `data_bits` payload bits behind coded-bit `llrs` (positive favours 1).
+pub fn decode(llrs: &[f32], fec: Fec, data_bits: usize) -> Vec<u8> {
+    if fec == Fec::None {
+        return llrs.iter().take(data_bits).map(|&llr| u8::from(llr > 0.0)).collect();
+    }
+    let pattern = fec.puncturing();
+    let steps = data_bits + TAIL_BITS;
+
+    // Path metric per state (correlation of the path's coded bits with the LLRs)
+    let mut metrics = vec![f32::NEG_INFINITY; STATES];
+    metrics[0] = 0.0;
+    let mut next = vec![0.0f32; STATES];
+    let mut survivors = vec![0u8; steps * STATES]; // low bit of each state's predecessor
+    let mut received = llrs.iter().copied();
+
+    for step in 0..steps {
+        let keep = pattern[step % pattern.len()];
+        let observed = keep.map(|kept| if kept { received.next().unwrap_or(0.0) } else { 0.0 });
+        let input_limit = if step < data_bits { 2 } else { 1 }; // the tail only shifts zeros in
+
+        next.fill(f32::NEG_INFINITY);
+        for (state, &metric) in metrics.iter().enumerate() {
+            if metric == f32::NEG_INFINITY {
+                continue; // not reachable yet
+            }
+            for input in 0..input_limit {
+                let register = input << TAIL_BITS | state;
+                let branch: f32 = GENERATORS
+                    .iter()
+                    .zip(observed)
+                    .map(|(&generator, llr)| if parity(register, generator) == 1 { llr } else { -llr })
+                    .sum();
+                let target = register >> 1;
+                if metric + branch > next[target] {
+                    next[target] = metric + branch;
+                    survivors[step * STATES + target] = (state & 1) as u8;
+                }
+            }
+        }
+        std::mem::swap(&mut metrics, &mut next);
+    }
+
+    // Trace back from state 0 (the tail terminates every path there)
+    let mut bits = vec![0u8; steps];
+    let mut state = 0usize;
+    for step in (0..steps).rev() {
+        bits[step] = (state >> (TAIL_BITS - 1)) as u8; // the input bit is the newest in the state
+        state = (state << 1) & (STATES - 1) | usize::from(survivors[step * STATES + state]);
+    }
+    bits.truncate(data_bits);
+    bits
+}
+
+fn parity(register: usize, generator: u32) -> u8 {
+    ((register as u32 & generator).count_ones() & 1) as u8
+}
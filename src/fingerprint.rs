@@ -0,0 +1,209 @@
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::decoder::{self, DecodeOptions};
+use crate::encoder::{self, EncodeOptions};
+use crate::energy::hash64;
+use crate::header::MAX_PAYLOAD_BYTES;
+
+// =============================================================================
+// Fingerprinting - per-recipient Tardos codewords and leak tracing
+// =============================================================================
+//
+// Each recipient gets a codeword from a Tardos code: column `i` has a bias
+// `p_i` drawn from the arcsine density (cut off at `t` and `1 - t`), and every
+// recipient's bit `i` is 1 with probability `p_i`. Colluders comparing copies
+// can only change the bits where their codewords differ (the marking
+// assumption), and Tardos codes stay traceable against any strategy for doing
+// so: the symmetric accuser (Škorić et al.) scores each recipient against the
+// leaked bits, and colluders end up with the highest scores.
+//
+// Codewords travel as an ordinary message, 6 bits per character in
+// '@'..=DEL, so that bits mixed by colluders still form valid ASCII and map
+// back to codeword bits one to one. A code of a few hundred bits outgrows a
+// frame, so embed with the segmented layout and QIM or phase coding.
+//
+// Codes are regenerated from their seed: the bias and bits are hashed from
+// `(seed, column, row)` the way carrier phases are, with no RNG state.
+
+/// Codeword bits carried by each payload character.
+pub const BITS_PER_CHAR: usize = 6;
+
+/// Longest codeword one message can carry.
+pub const MAX_CODE_BITS: usize = MAX_PAYLOAD_BYTES * BITS_PER_CHAR;
+
+const CHAR_BASE: u8 = 0x40; // '@': the top two bits are fixed, the low six carry the codeword
+
+/// A Tardos code: column biases and one codeword per recipient.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TardosCode {
+    probabilities: Vec<f64>,
+    codewords: Vec<Vec<u8>>,
+}
+
+/// One recipient's accusation score against a leaked copy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Suspect {
+    pub user: usize,
+    pub score: f64,
+}
+
+/// How colluders combine their copies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollusionAttack {
+    /// Sample-wise mean of all copies
+    Average,
+    /// Cut-and-paste: consecutive blocks of `block_samples` taken from each copy in turn
+    Interleave { block_samples: usize },
+}
+
+/// Code length for catching a coalition of up to `colluders` with false-accusation probability `epsilon`
+/// (the symmetric Tardos bound, π²/2 · c² · ln(1/ε)).
+pub fn code_length(colluders: usize, epsilon: f64) -> usize {
+    assert!(colluders > 0, "a code must resist at least one colluder");
+    assert!(epsilon > 0.0 && epsilon < 1.0, "epsilon must lie in (0, 1)");
+    let c = colluders as f64;
+    (PI * PI / 2.0 * c * c * (1.0 / epsilon).ln()).ceil() as usize
+}
+
+impl TardosCode {
+    /// Codewords of `length` bits for `users` recipients, tuned against `colluders`; `seed` fixes the code.
+    pub fn new(users: usize, length: usize, colluders: usize, seed: u64) -> Self {
+        assert!(colluders > 0, "a code must resist at least one colluder");
+        assert!(length <= MAX_CODE_BITS, "codewords of {length} bits exceed the {MAX_CODE_BITS}-bit payload");
+
+        // Arcsine density on [t, 1 - t]: p = sin²(r) with r uniform on [r_t, π/2 - r_t]
+        let cutoff = 1.0 / (300.0 * colluders as f64);
+        let r_min = cutoff.sqrt().asin();
+        let probabilities: Vec<f64> = (0..length)
+            .map(|column| (r_min + (FRAC_PI_2 - 2.0 * r_min) * uniform(seed, column, 0)).sin().powi(2))
+            .collect();
+        let codewords = (0..users)
+            .map(|user| {
+                let draws = probabilities.iter().enumerate();
+                draws.map(|(column, &p)| u8::from(uniform(seed, column, user + 1) < p)).collect()
+            })
+            .collect();
+        Self { probabilities, codewords }
+    }
+
+    pub fn length(&self) -> usize {
+        self.probabilities.len()
+    }
+
+    pub fn users(&self) -> usize {
+        self.codewords.len()
+    }
+
+    /// Bias of each column.
+    pub fn probabilities(&self) -> &[f64] {
+        &self.probabilities
+    }
+
+    pub fn codeword(&self, user: usize) -> &[u8] {
+        &self.codewords[user]
+    }
+
+    /// Symmetric Tardos scores of every recipient against `leaked` bits, highest first.
+    pub fn accuse(&self, leaked: &[u8]) -> Vec<Suspect> {
+        let mut suspects: Vec<Suspect> = self
+            .codewords
+            .iter()
+            .enumerate()
+            .map(|(user, codeword)| {
+                let columns = leaked.iter().zip(codeword).zip(&self.probabilities);
+                let score = columns.map(|((&y, &x), &p)| column_score(y, x, p)).sum();
+                Suspect { user, score }
+            })
+            .collect();
+        suspects.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.user.cmp(&b.user)));
+        suspects
+    }
+}
+
+/// Score contribution of one column: agreeing with a rare symbol counts for more than with a common one.
+fn column_score(leaked: u8, bit: u8, p: f64) -> f64 {
+    let (p_y, q_y) = if leaked == 1 { (p, 1.0 - p) } else { (1.0 - p, p) }; // probability of the leaked symbol
+    if bit == leaked {
+        (q_y / p_y).sqrt()
+    } else {
+        -(p_y / q_y).sqrt()
+    }
+}
+
+/// Deterministic draw in [0, 1) for `(seed, column, row)`.
+fn uniform(seed: u64, column: usize, row: usize) -> f64 {
+    let x = hash64(seed ^ ((column as u64) << 32 | row as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Message carrying `codeword`, `BITS_PER_CHAR` bits per character (MSB first, zero-padded).
+pub fn codeword_message(codeword: &[u8]) -> String {
+    codeword
+        .chunks(BITS_PER_CHAR)
+        .map(|chunk| {
+            let value = chunk.iter().fold(0u8, |acc, &bit| (acc << 1) | (bit & 1)) << (BITS_PER_CHAR - chunk.len());
+            char::from(CHAR_BASE | value)
+        })
+        .collect()
+}
+
+/// First `length` codeword bits of a decoded payload (missing characters read as zeros).
+pub fn message_codeword(raw_bytes: &[u8], length: usize) -> Vec<u8> {
+    let mut bits: Vec<u8> = raw_bytes
+        .iter()
+        .flat_map(|&byte| (0..BITS_PER_CHAR).rev().map(move |shift| (byte >> shift) & 1))
+        .take(length)
+        .collect();
+    bits.resize(length, 0);
+    bits
+}
+
+/// Embed `user`'s codeword (options as for any message; segmented QIM or phase for long codes).
+pub fn embed_fingerprint(
+    samples: &[f32],
+    sample_rate: u32,
+    code: &TardosCode,
+    user: usize,
+    options: &EncodeOptions,
+) -> Result<Vec<f32>, String> {
+    let message = codeword_message(code.codeword(user));
+    encoder::encode_audio_samples_with_options(samples, sample_rate, &message, options).map(|(encoded, _)| encoded)
+}
+
+/// Codeword bits read back from a (possibly colluded) copy.
+pub fn extract_fingerprint(samples: &[f32], sample_rate: u32, length: usize, options: &DecodeOptions) -> Vec<u8> {
+    let (decoded, _) = decoder::decode_audio_samples_with_options(samples, sample_rate, options);
+    message_codeword(&decoded.raw_bytes, length)
+}
+
+/// Combine marked copies of the same recording (e.g. `encode_audio_samples` outputs) as colluders would.
+pub fn collude(copies: &[Vec<f32>], attack: CollusionAttack) -> Vec<f32> {
+    assert!(!copies.is_empty(), "collusion needs at least one copy");
+    let len = copies.iter().map(Vec::len).min().unwrap_or(0);
+    match attack {
+        CollusionAttack::Average => {
+            let scale = 1.0 / copies.len() as f32;
+            (0..len).map(|i| copies.iter().map(|copy| copy[i]).sum::<f32>() * scale).collect()
+        }
+        CollusionAttack::Interleave { block_samples } => {
+            assert!(block_samples > 0, "interleaved blocks must be non-empty");
+            (0..len).map(|i| copies[(i / block_samples) % copies.len()][i]).collect()
+        }
+    }
+}
+
+/// Fingerprint `samples` for each of `colluders` and combine their copies with `attack`.
+pub fn simulate_collusion(
+    samples: &[f32],
+    sample_rate: u32,
+    code: &TardosCode,
+    colluders: &[usize],
+    attack: CollusionAttack,
+    options: &EncodeOptions,
+) -> Result<Vec<f32>, String> {
+    let copies: Vec<Vec<f32>> = colluders
+        .iter()
+        .map(|&user| embed_fingerprint(samples, sample_rate, code, user, options))
+        .collect::<Result<_, _>>()?;
+    Ok(collude(&copies, attack))
+}
@@ -0,0 +1,230 @@
+use crate::encoder::EmbeddingMode;
+
+// =============================================================================
+// Bitstream header - self-describing format after the pilot
+// =============================================================================
+//
+// Version 1 layout (32 bits, MSB first, straight after the pilot):
+//
+//   magic 3 | version 2 | scheme 3 | FEC 2 | frame 2 | flags 4 | length 8 | CRC-8
+//
+// The CRC covers the 24 bits before it, so the decoder can trust the length
+// instead of guessing it. All 32 bits are then XORed with a fixed whitening
+// mask: the fields are mostly zeros, and schemes that score a bin against its
+// neighbours (multiplicative, additive) cannot see inside a long run of equal
+// bits.
+//
+// Legacy streams (version 0) carry a bare 16-bit length here. Below 256 bytes
+// it starts with eight 0 bits, which never unwhiten to the magic. The encoder
+// still writes them when a version 1 stream would not fit the frame, so small
+// frames keep the capacity they had before headers.
+
+/// Header bits between the pilot and the payload, CRC included.
+pub const HEADER_BITS: usize = 32;
+
+/// Newest layout this build writes and reads.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Longest payload (bytes) a version 1 header can describe; longer messages use the legacy layout.
+pub const MAX_PAYLOAD_BYTES: usize = 255;
+
+/// Scheme code for anything that is not a built-in [`EmbeddingMode`].
+pub const CUSTOM_SCHEME: u8 = 7;
+
+/// FEC code for an unprotected payload.
+pub const FEC_NONE: u8 = 0;
+
+/// Flag bit: the stream is split over frames (see [`crate::segment`]).
+pub const FLAG_SEGMENTED: u8 = 0b0001;
+
+/// Flag bit: the payload ends in an authentication tag (see [`crate::auth`]).
+pub const FLAG_AUTHENTICATED: u8 = 0b0010;
+
+/// Flag bit: the payload is sealed with ChaCha20-Poly1305 (see [`crate::cipher`]).
+pub const FLAG_ENCRYPTED: u8 = 0b0100;
+
+/// Flag bit: the message is packed (see [`crate::packing`]).
+pub const FLAG_PACKED: u8 = 0b1000;
+
+const MAGIC: u8 = 0b101;
+const WHITENING: u32 = 0x6996_9669; // Thue-Morse bits: no run longer than two
+const CRC_POLY: u8 = 0x07; // x^8 + x^2 + x + 1
+
+/// Frame durations with a 2-bit code; code 3 means "something else".
+pub const FRAME_CODES_MS: [u32; 3] = [20, 32, 64];
+
+/// Decoded version 1 header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub scheme: u8,      // EmbeddingMode::ALL index, or CUSTOM_SCHEME
+    pub fec: u8,         // FEC_NONE or a `crate::fec::Fec` code
+    pub frame_code: u8,  // see `frame_duration_ms`
+    pub flags: u8,       // 4 bits, FLAG_* (the rest reserved, 0)
+    pub length: usize,   // payload bytes
+}
+
+impl Header {
+    pub fn new(scheme_id: &str, frame_duration_ms: u32, length: usize) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            scheme: scheme_code(scheme_id),
+            fec: FEC_NONE,
+            frame_code: frame_code(frame_duration_ms),
+            flags: 0,
+            length,
+        }
+    }
+
+    /// Frame duration the encoder used, if it is one of the coded ones.
+    pub fn frame_duration_ms(&self) -> Option<u32> {
+        FRAME_CODES_MS.get(usize::from(self.frame_code)).copied()
+    }
+
+    /// Built-in scheme named by the header, if any.
+    pub fn mode(&self) -> Option<EmbeddingMode> {
+        EmbeddingMode::ALL.get(usize::from(self.scheme)).copied()
+    }
+
+    /// The `HEADER_BITS` bits written after the pilot.
+    pub fn to_bits(&self) -> Vec<u8> {
+        let mut bits = Vec::with_capacity(HEADER_BITS);
+        push_field(&mut bits, MAGIC.into(), 3);
+        push_field(&mut bits, self.version.into(), 2);
+        push_field(&mut bits, self.scheme.into(), 3);
+        push_field(&mut bits, self.fec.into(), 2);
+        push_field(&mut bits, self.frame_code.into(), 2);
+        push_field(&mut bits, self.flags.into(), 4);
+        push_field(&mut bits, self.length, 8);
+        let crc = crc8(&bits);
+        push_field(&mut bits, crc.into(), 8);
+        xor_mask(&mut bits, WHITENING);
+        bits
+    }
+
+    /// Whether the payload is split over frames rather than repeated in each.
+    pub fn is_segmented(&self) -> bool {
+        self.flags & FLAG_SEGMENTED != 0
+    }
+
+    /// Whether the last [`crate::auth::TAG_BYTES`] of the payload are an HMAC tag over the rest.
+    pub fn is_authenticated(&self) -> bool {
+        self.flags & FLAG_AUTHENTICATED != 0
+    }
+
+    /// Whether the payload (before any authentication tag) is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    /// Whether the message was packed before any encryption and authentication.
+    pub fn is_packed(&self) -> bool {
+        self.flags & FLAG_PACKED != 0
+    }
+
+    /// Parse the bits after the pilot; `None` unless the magic, version and CRC all check out.
+    ///
+    /// A single flipped bit is corrected: the CRC's Hamming distance of 4 means
+    /// no other valid header lies within one flip of a word with two errors or fewer.
+    pub fn from_bits(bits: &[u8]) -> Option<Self> {
+        let mut bits = bits.get(..HEADER_BITS)?.to_vec();
+        xor_mask(&mut bits, WHITENING);
+        parse_correcting(&mut bits, Self::parse)
+    }
+
+    /// Whether the bits after the pilot open with the magic: a version 1 stream, even if its header fails.
+    /// Legacy streams are two flips away (their leading zeros unwhiten to `011`).
+    pub fn has_magic(bits: &[u8]) -> bool {
+        let Some(magic) = bits.get(..3) else {
+            return false;
+        };
+        let mut magic = magic.to_vec();
+        xor_mask(&mut magic, WHITENING >> (HEADER_BITS - 3));
+        Fields(&magic).take(3) == usize::from(MAGIC)
+    }
+
+    /// [`Header::from_bits`] without the correction: every bit must check out.
+    pub fn from_bits_exact(bits: &[u8]) -> Option<Self> {
+        let mut bits = bits.get(..HEADER_BITS)?.to_vec();
+        xor_mask(&mut bits, WHITENING);
+        Self::parse(&bits)
+    }
+
+    fn parse(bits: &[u8]) -> Option<Self> {
+        let mut fields = Fields(bits);
+        if fields.take(3) != usize::from(MAGIC) {
+            return None;
+        }
+        let header = Self {
+            version: fields.take(2) as u8,
+            scheme: fields.take(3) as u8,
+            fec: fields.take(2) as u8,
+            frame_code: fields.take(2) as u8,
+            flags: fields.take(4) as u8,
+            length: fields.take(8),
+        };
+        let crc = fields.take(8) as u8;
+        let valid = crc == crc8(&bits[..HEADER_BITS - 8]) && (1..=FORMAT_VERSION).contains(&header.version);
+        valid.then_some(header)
+    }
+}
+
+/// Header code for a scheme id.
+pub fn scheme_code(id: &str) -> u8 {
+    id.parse::<EmbeddingMode>()
+        .ok()
+        .and_then(|mode| EmbeddingMode::ALL.iter().position(|&m| m == mode))
+        .map_or(CUSTOM_SCHEME, |idx| idx as u8)
+}
+
+/// Header code for a frame duration.
+pub fn frame_code(frame_duration_ms: u32) -> u8 {
+    FRAME_CODES_MS
+        .iter()
+        .position(|&ms| ms == frame_duration_ms)
+        .unwrap_or(FRAME_CODES_MS.len()) as u8
+}
+
+/// Bitwise CRC-8 (poly 0x07, init 0) over a bit sequence.
+pub fn crc8(bits: &[u8]) -> u8 {
+    bits.iter().fold(0u8, |crc, &bit| {
+        let feedback = (crc >> 7) ^ (bit & 1);
+        let crc = crc << 1;
+        if feedback == 1 { crc ^ CRC_POLY } else { crc }
+    })
+}
+
+/// XOR with the low `bits.len()` bits of `mask`, MSB first (its own inverse).
+pub(crate) fn xor_mask(bits: &mut [u8], mask: u32) {
+    let width = bits.len();
+    for (idx, bit) in bits.iter_mut().enumerate() {
+        *bit ^= ((mask >> (width - 1 - idx)) & 1) as u8;
+    }
+}
+
+/// `parse`, or else `parse` of the first single-bit flip that checks out.
+pub(crate) fn parse_correcting<T>(bits: &mut [u8], parse: impl Fn(&[u8]) -> Option<T>) -> Option<T> {
+    parse(bits).or_else(|| {
+        (0..bits.len()).find_map(|idx| {
+            bits[idx] ^= 1;
+            let parsed = parse(bits);
+            bits[idx] ^= 1;
+            parsed
+        })
+    })
+}
+
+pub(crate) fn push_field(bits: &mut Vec<u8>, value: usize, width: usize) {
+    bits.extend((0..width).rev().map(|shift| ((value >> shift) & 1) as u8));
+}
+
+/// MSB-first field reader over a bit slice.
+pub(crate) struct Fields<'a>(pub(crate) &'a [u8]);
+
+impl Fields<'_> {
+    pub(crate) fn take(&mut self, width: usize) -> usize {
+        let (field, rest) = self.0.split_at(width);
+        self.0 = rest;
+        field.iter().fold(0, |value, &bit| (value << 1) | usize::from(bit & 1))
+    }
+}
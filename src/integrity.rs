@@ -0,0 +1,182 @@
+use crate::decoder::{self, frame_alignment, hard_bits, DecodeOptions, FrameRead, FrameSource, LENGTH_HEADER_BITS};
+use crate::engine::Engine;
+use crate::header::Header;
+use crate::scheme::WatermarkScheme;
+use crate::segment::{self, SegmentField};
+
+// =============================================================================
+// Integrity map - per-frame watermark checks for tamper localisation
+// =============================================================================
+//
+// Every frame of a marked recording carries the pilot and a CRC-checked field
+// (the header in the repeated layout, the segment field in the segmented one),
+// so each frame can be checked on its own. Edits show up as runs of failing
+// frames: no pilot where audio was muted or replaced with unmarked material,
+// a pilot with a bad checksum where the mark was damaged. Frames below the
+// energy gate carry no mark by design and are reported as quiet, not flagged.
+//
+// The checksum must match exactly (no single-bit correction as when decoding),
+// which keeps unmarked audio from passing as intact by chance. The pilot check
+// alone is loose, so unmarked audio mostly shows up as corrupted, not missing.
+//
+// QIM and phase coding can be read from one frame. The spectral-contrast
+// schemes (multiplicative, additive) need many frames per bit: most of their
+// frames would fail on their own, marked or not, so they are refused rather
+// than mapped. Cycling schemes (echo) have no per-frame pilot at all, so their
+// map is empty.
+
+/// What one frame's check found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameStatus {
+    /// Pilot and checksum both matched
+    Intact,
+    /// Pilot found, but the header or segment field failed its CRC
+    Corrupted,
+    /// No pilot: the mark is absent (muted, replaced, or never embedded)
+    Missing,
+    /// Below the energy gate, so there was nothing to check
+    Quiet,
+}
+
+/// One analysis frame of the recording.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameCheck {
+    pub start_seconds: f32,
+    pub end_seconds: f32,
+    pub status: FrameStatus,
+}
+
+/// Consecutive frames flagged with the same status (never `Intact` or `Quiet`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TamperSpan {
+    pub start_seconds: f32,
+    pub end_seconds: f32,
+    pub status: FrameStatus,
+}
+
+/// Per-frame integrity map (whole frames only) and the time ranges it flags.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IntegrityReport {
+    pub frames: Vec<FrameCheck>,
+    pub spans: Vec<TamperSpan>,
+}
+
+impl IntegrityReport {
+    fn from_frames(frames: Vec<FrameCheck>) -> Self {
+        let mut spans: Vec<TamperSpan> = Vec::new();
+        for frame in &frames {
+            if matches!(frame.status, FrameStatus::Intact | FrameStatus::Quiet) {
+                continue;
+            }
+            match spans.last_mut() {
+                Some(span) if span.status == frame.status && span.end_seconds == frame.start_seconds => {
+                    span.end_seconds = frame.end_seconds; // extend the current run
+                }
+                _ => spans.push(TamperSpan {
+                    start_seconds: frame.start_seconds,
+                    end_seconds: frame.end_seconds,
+                    status: frame.status,
+                }),
+            }
+        }
+        Self { frames, spans }
+    }
+
+    /// Fraction of the checkable (non-quiet) frames that are intact.
+    pub fn intact_fraction(&self) -> f32 {
+        let checked = self.frames.iter().filter(|frame| frame.status != FrameStatus::Quiet);
+        let (intact, total) = checked.fold((0usize, 0usize), |(intact, total), frame| {
+            (intact + usize::from(frame.status == FrameStatus::Intact), total + 1)
+        });
+        intact as f32 / total.max(1) as f32
+    }
+}
+
+/// Integrity map for a built-in scheme (the scheme, frames and layout read from the header unless `options` set them; the gate must match the encoder).
+/// Errors for schemes whose frames cannot be checked one at a time.
+pub fn integrity_map(samples: &[f32], sample_rate: u32, options: &DecodeOptions) -> Result<IntegrityReport, String> {
+    let mut engine = Engine::new();
+    engine.integrity_map(samples, sample_rate, options)
+}
+
+/// Integrity map generic over the [`WatermarkScheme`] (`options.mode` is ignored)
+pub fn integrity_map_with_scheme<S: WatermarkScheme + ?Sized>(
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    options: &DecodeOptions,
+) -> Result<IntegrityReport, String> {
+    let mut engine = Engine::new();
+    check_with_engine(&mut engine, scheme, samples, sample_rate, options)
+}
+
+/// Integrity map body shared by the free functions and [`Engine`]
+pub(crate) fn check_with_engine<S: WatermarkScheme + ?Sized>(
+    engine: &mut Engine,
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    options: &DecodeOptions,
+) -> Result<IntegrityReport, String> {
+    if !scheme.reads_single_frames() {
+        return Err(format!("{} frames cannot be checked one at a time; use qim or phase", scheme.id()));
+    }
+    let options = &decoder::configure_with_scheme(engine, scheme, samples, sample_rate, options);
+    let frame_len = options.frame_len(sample_rate);
+    let ctx = engine.context(frame_len.next_power_of_two().max(2));
+    let mut source = FrameSource::new(scheme, samples, frame_len, sample_rate, options)?;
+    let capacity = scheme.capacity(ctx.buffers.spectrum.len());
+    if capacity < source.pilot.len() + LENGTH_HEADER_BITS {
+        return Ok(IntegrityReport::default()); // no per-frame pilot to check
+    }
+
+    let (offset, first_index) = if options.search_alignment { frame_alignment(ctx, &source) } else { (0, 0) };
+    source.samples = &samples[offset..];
+    source.first_index = first_index;
+
+    let reads = decoder::read_frames(ctx, &source);
+    let statuses: Vec<FrameStatus> = source
+        .samples
+        .chunks_exact(frame_len) // a partial tail frame is too short to check
+        .zip(reads.iter())
+        .map(|(frame, read)| check_frame(&source, capacity, frame, &read))
+        .collect();
+
+    let seconds = |sample: usize| sample as f32 / sample_rate as f32;
+    let frames = statuses
+        .into_iter()
+        .enumerate()
+        .map(|(index, status)| {
+            let start = offset + index * frame_len;
+            FrameCheck {
+                start_seconds: seconds(start),
+                end_seconds: seconds(start + frame_len),
+                status,
+            }
+        })
+        .collect();
+    Ok(IntegrityReport::from_frames(frames))
+}
+
+/// Pilot check, then the CRC of the field after it (header, or segment field when segmented).
+fn check_frame<S: WatermarkScheme + ?Sized>(source: &FrameSource<S>, capacity: usize, frame: &[f32], read: &FrameRead) -> FrameStatus {
+    if source.options.energy_gate.is_some_and(|gate| gate.is_quiet(frame)) {
+        return FrameStatus::Quiet;
+    }
+    let Some((threshold, inverted)) = read.verdict(&source.pilot, source.detection) else {
+        return FrameStatus::Missing;
+    };
+
+    let field = hard_bits(&read.scores[source.pilot.len()..], threshold, inverted);
+    let segmented = source.options.is_segmented() && segment::chunk_bits_with_pilot(capacity, source.pilot.len()).is_some();
+    let checksum = if segmented {
+        SegmentField::from_bits_exact(&field).is_some()
+    } else {
+        Header::from_bits_exact(&field).is_some()
+    };
+    if checksum {
+        FrameStatus::Intact
+    } else {
+        FrameStatus::Corrupted
+    }
+}
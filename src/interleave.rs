@@ -0,0 +1,104 @@
+use crate::decoder::LENGTH_HEADER_BITS;
+use crate::energy::hash64;
+
+// =============================================================================
+// Bit interleaving - spreading neighbouring bits across the band
+// =============================================================================
+//
+// Neighbouring bins leak into each other and the contrast schemes score a bin
+// against its neighbours, so bit errors come in bursts and a run of equal
+// bits reads differently from an alternating pattern. An interleaver permutes
+// each frame's bitstream before it is mapped to bins, and the decoder puts
+// the scores back in stream order before anything else looks at them, so a
+// burst of bad bins lands on bits scattered over the stream.
+//
+// The permutation covers the scheme's whole per-frame capacity, the pilot and
+// header included, because the decoder has to undo it before it can read the
+// header. Frames whose bitstream is shorter than the capacity are padded with
+// alternating filler bits, so interleaving marks every carrier bin. The
+// decoder must be given the encoder's interleaver; it is not signalled.
+// Cycling schemes (echo) carry one bit per frame, so they are left alone.
+//
+// Interleaving is meant for the per-bin schemes (QIM, phase), ahead of an
+// error-correcting code. The contrast schemes (multiplicative, additive) read
+// a bin against its neighbours, and a scattered pattern lowers that contrast:
+// they decode no better interleaved, and often worse.
+
+/// How bitstream positions are spread over a frame's bins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interleaver {
+    /// Bit `i` goes to bin `i`
+    #[default]
+    None,
+    /// Written into `rows` rows, read out column by column
+    Block { rows: usize },
+    /// Seeded pseudo-random permutation
+    Random { seed: u64 },
+}
+
+impl Interleaver {
+    /// [`Interleaver::order`] for a scheme carrying `capacity` bits per frame behind a `pilot_len`-bit pilot;
+    /// `None` if it cycles instead. `Err` for settings [`Interleaver::check`] rejects.
+    pub fn frame_order(&self, capacity: usize, pilot_len: usize) -> Result<Option<Vec<usize>>, String> {
+        self.check()?;
+        if capacity < pilot_len.saturating_add(LENGTH_HEADER_BITS) {
+            return Ok(None); // the stream is spread over frames, not bins
+        }
+        self.order(capacity)
+    }
+
+    /// `Err` for a block interleaver without rows.
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            Self::Block { rows: 0 } => Err("a block interleaver needs at least one row".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Stream position carried by each of `capacity` bin positions, or `None` for the identity.
+    /// `Err` for settings [`Interleaver::check`] rejects.
+    pub fn order(&self, capacity: usize) -> Result<Option<Vec<usize>>, String> {
+        self.check()?;
+        Ok(match *self {
+            Self::None => None,
+            Self::Block { rows } => {
+                let columns = capacity.div_ceil(rows);
+                let cells = (0..columns).flat_map(|column| (0..rows).map(move |row| row * columns + column));
+                Some(cells.filter(|&cell| cell < capacity).collect())
+            }
+            Self::Random { seed } => {
+                let mut order: Vec<usize> = (0..capacity).collect();
+                for i in (1..capacity).rev() {
+                    order.swap(i, (mix(seed, i) % (i as u64 + 1)) as usize); // Fisher-Yates
+                }
+                Some(order)
+            }
+        })
+    }
+}
+
+/// `bits` padded to `order.len()` with filler, then permuted onto bin positions.
+pub fn interleave(bits: &[u8], order: &[usize]) -> Vec<u8> {
+    order
+        .iter()
+        .map(|&position| bits.get(position).copied().unwrap_or((position % 2) as u8))
+        .collect()
+}
+
+/// Per-bin `scores` put back in stream order into `out` (scores past `order` are kept as they are,
+/// and positions whose bin has no score read 0).
+pub fn deinterleave_into(scores: &[f32], order: &[usize], out: &mut Vec<f32>) {
+    out.clear();
+    out.resize(scores.len().max(order.len()), 0.0);
+    for (&score, &position) in scores.iter().zip(order) {
+        out[position] = score;
+    }
+    if let Some(rest) = scores.get(order.len()..) {
+        out[order.len()..].copy_from_slice(rest);
+    }
+}
+
+/// Deterministic 64-bit hash of `(seed, index)`.
+fn mix(seed: u64, index: usize) -> u64 {
+    hash64(seed ^ (index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
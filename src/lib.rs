@@ -1,18 +1,56 @@
+pub mod additive;
+pub mod auth;
+pub mod cipher;
 pub mod decoder;
+pub mod echo;
 pub mod encoder;
+pub mod energy;
+pub mod engine;
+pub mod fec;
+pub mod fingerprint;
+pub mod header;
+pub mod integrity;
+pub mod interleave;
+pub mod packing;
+pub mod phase;
+pub mod pilot;
+pub mod qim;
+pub mod scheme;
+pub mod segment;
+pub mod threshold;
+pub mod timeline;
+
+use std::cell::RefCell;
 
 use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
 
 // Re-export the encoder and decoder modules
-pub use decoder::DecodedWatermark;
-pub use encoder::PILOT_PATTERN;
+pub use decoder::{DecodeOptions, DecodedWatermark};
+pub use encoder::{EmbeddingMode, EncodeOptions, PayloadLayout, PILOT_PATTERN};
+pub use energy::{EnergyGate, QuietFramePolicy};
+pub use engine::Engine;
+pub use fec::Fec;
+pub use fingerprint::{CollusionAttack, TardosCode};
+pub use header::Header;
+pub use integrity::{FrameStatus, IntegrityReport};
+pub use interleave::Interleaver;
+pub use packing::Packing;
+pub use pilot::Pilot;
+pub use scheme::WatermarkScheme;
+pub use timeline::TimelineEntry;
+
+thread_local! {
+    /// Engine shared by the JS entry points so FFT plans survive between calls
+    static ENGINE: RefCell<Engine> = RefCell::new(Engine::new());
+}
 
 /// Struct to hold decoded watermark data for JS
 #[derive(Serialize, Deserialize)]
 pub struct DecodedResult {
     pub message: String,
     pub raw_bytes: Vec<u8>,
+    pub authenticated: Option<bool>, // tag check against the caller's key (None without one)
 }
 
 /// Struct to hold decoding visualization data for JS
@@ -25,6 +63,7 @@ pub struct DecodeVisualizationResult {
     pub avg_high: f32,
     pub avg_low: f32,
     pub inverted: bool,
+    pub llrs: Vec<f32>,
     pub first_frame: Vec<f32>,
 }
 
@@ -36,6 +75,23 @@ pub struct DecodeResult {
     pub visualization: DecodeVisualizationResult,
 }
 
+/// One flagged time range of the integrity map, for JS
+#[derive(Serialize, Deserialize)]
+pub struct TamperSpanResult {
+    pub start_seconds: f32,
+    pub end_seconds: f32,
+    pub status: String, // "corrupted" or "missing"
+}
+
+/// Struct to hold the integrity map for JS
+#[derive(Serialize, Deserialize)]
+pub struct IntegrityResult {
+    pub frame_seconds: Vec<f32>,   // start of each frame
+    pub frame_status: Vec<String>, // "intact", "corrupted", "missing" or "quiet" per frame
+    pub spans: Vec<TamperSpanResult>,
+    pub intact_fraction: f32,
+}
+
 /// Struct to hold encoding visualization data for JS
 #[derive(Serialize, Deserialize)]
 pub struct EncodeVisualizationResult {
@@ -70,13 +126,15 @@ pub fn encode_audio(
     frame_duration_ms: u32,
     strength_percent: u32,
 ) -> Vec<f32> {
-    encoder::encode_audio_samples(
-        &samples,
-        sample_rate,
-        &message,
-        frame_duration_ms,
-        strength_percent,
-    )
+    ENGINE.with(|engine| {
+        engine.borrow_mut().encode_audio_samples(
+            &samples,
+            sample_rate,
+            &message,
+            frame_duration_ms,
+            strength_percent,
+        )
+    })
 }
 
 /// Encode a message into audio samples with visualization data
@@ -98,13 +156,15 @@ pub fn encode_audio_with_viz(
     frame_duration_ms: u32,
     strength_percent: u32,
 ) -> String {
-    let (encoded_samples, viz) = encoder::encode_audio_samples_with_viz(
-        &samples,
-        sample_rate,
-        &message,
-        frame_duration_ms,
-        strength_percent,
-    );
+    let (encoded_samples, viz) = ENGINE.with(|engine| {
+        engine.borrow_mut().encode_audio_samples_with_viz(
+            &samples,
+            sample_rate,
+            &message,
+            frame_duration_ms,
+            strength_percent,
+        )
+    });
     
     let result = EncodeResult {
         encoded_samples,
@@ -128,10 +188,11 @@ pub fn encode_audio_with_viz(
 /// Decoded watermark containing the message and raw bytes as JSON string
 #[wasm_bindgen]
 pub fn decode_audio(samples: Vec<f32>, sample_rate: u32) -> String {
-    let result = decoder::decode_audio_samples(&samples, sample_rate);
+    let result = ENGINE.with(|engine| engine.borrow_mut().decode_audio_samples(&samples, sample_rate));
     let decoded_result = DecodedResult {
         message: result.message,
         raw_bytes: result.raw_bytes,
+        authenticated: result.authenticated,
     };
     serde_json::to_string(&decoded_result).unwrap()
 }
@@ -146,7 +207,8 @@ pub fn decode_audio(samples: Vec<f32>, sample_rate: u32) -> String {
 /// JSON string containing decoded message and visualization data
 #[wasm_bindgen]
 pub fn decode_audio_with_viz(samples: Vec<f32>, sample_rate: u32) -> String {
-    let (decoded, viz) = decoder::decode_audio_samples_with_viz(&samples, sample_rate);
+    let (decoded, viz) =
+        ENGINE.with(|engine| engine.borrow_mut().decode_audio_samples_with_viz(&samples, sample_rate));
     let result = DecodeResult {
         message: decoded.message,
         raw_bytes: decoded.raw_bytes,
@@ -158,9 +220,289 @@ pub fn decode_audio_with_viz(samples: Vec<f32>, sample_rate: u32) -> String {
             avg_high: viz.avg_high,
             avg_low: viz.avg_low,
             inverted: viz.inverted,
+            llrs: viz.llrs,
             first_frame: viz.first_frame,
         },
     };
     serde_json::to_string(&result).unwrap()
 }
 
+/// Names accepted by the `*_with_scheme` functions
+#[wasm_bindgen]
+pub fn scheme_names() -> Vec<String> {
+    EmbeddingMode::ALL
+        .iter()
+        .map(|mode| mode.scheme().id().to_string())
+        .collect()
+}
+
+/// Encode a message with a scheme picked by name
+/// 
+/// # Arguments
+/// * `samples` - Audio samples as f32 array (normalized to [-1.0, 1.0])
+/// * `sample_rate` - Sample rate in Hz
+/// * `message` - Message string to encode
+/// * `scheme` - One of [`scheme_names`]
+/// * `frame_duration_ms` - Frame duration in milliseconds (default: 32)
+/// * `strength_percent` - Watermark strength as percentage (default: 15)
+/// 
+/// # Returns
+/// Encoded audio samples as Vec<f32>, or an error for an unknown scheme
+#[wasm_bindgen]
+pub fn encode_audio_with_scheme(
+    samples: Vec<f32>,
+    sample_rate: u32,
+    message: String,
+    scheme: String,
+    frame_duration_ms: u32,
+    strength_percent: u32,
+) -> Result<Vec<f32>, JsError> {
+    let mode: EmbeddingMode = scheme.parse().map_err(|err: String| JsError::new(&err))?;
+    let options = EncodeOptions {
+        mode,
+        ..EncodeOptions::new(frame_duration_ms, strength_percent)
+    };
+    let (encoded, _) = ENGINE
+        .with(|engine| engine.borrow_mut().encode_with_options(&samples, sample_rate, &message, &options))
+        .expect("unkeyed messages always encode");
+    Ok(encoded)
+}
+
+/// Decode a message with a scheme picked by name
+/// 
+/// # Arguments
+/// * `samples` - Audio samples as f32 array (normalized to [-1.0, 1.0])
+/// * `sample_rate` - Sample rate in Hz
+/// * `scheme` - One of [`scheme_names`]
+/// * `strength_percent` - The encoder's strength (default: 15). The header does not record it, and
+///   QIM's lattice step follows it, so "qim" needs the encoder's value; the other schemes ignore it
+/// 
+/// # Returns
+/// Decoded watermark as JSON string, or an error for an unknown scheme
+#[wasm_bindgen]
+pub fn decode_audio_with_scheme(
+    samples: Vec<f32>,
+    sample_rate: u32,
+    scheme: String,
+    strength_percent: u32,
+) -> Result<String, JsError> {
+    let mode: EmbeddingMode = scheme.parse().map_err(|err: String| JsError::new(&err))?;
+    let options = DecodeOptions {
+        mode: Some(mode),
+        strength_percent: Some(strength_percent),
+        ..DecodeOptions::default()
+    };
+    let (result, _) = ENGINE.with(|engine| engine.borrow_mut().decode_with_options(&samples, sample_rate, &options));
+    let decoded_result = DecodedResult {
+        message: result.message,
+        raw_bytes: result.raw_bytes,
+        authenticated: result.authenticated,
+    };
+    Ok(serde_json::to_string(&decoded_result).unwrap())
+}
+
+/// Encode a message encrypted under a shared secret, with a scheme picked by name
+/// (segmented layout, which the encryption overhead needs; use "qim" or "phase")
+///
+/// Encryption is deterministic (the nonce is a synthetic IV of the header and message, see
+/// [`cipher`]): the same message under the same key always gives the same payload, so repeated
+/// messages can be told apart from distinct ones. Make messages unique to hide repeats.
+/// 
+/// # Arguments
+/// * `samples` - Audio samples as f32 array (normalized to [-1.0, 1.0])
+/// * `sample_rate` - Sample rate in Hz
+/// * `message` - Message string to encode
+/// * `scheme` - One of [`scheme_names`]
+/// * `key` - Shared secret the decoder needs to read the message
+/// * `frame_duration_ms` - Frame duration in milliseconds (default: 32)
+/// * `strength_percent` - Watermark strength as percentage (default: 15)
+/// 
+/// # Returns
+/// Encoded audio samples as Vec<f32>, or an error for an unknown scheme, one other than "qim" or "phase",
+/// or a message too long to seal
+#[wasm_bindgen]
+pub fn encode_audio_encrypted(
+    samples: Vec<f32>,
+    sample_rate: u32,
+    message: String,
+    scheme: String,
+    key: Vec<u8>,
+    frame_duration_ms: u32,
+    strength_percent: u32,
+) -> Result<Vec<f32>, JsError> {
+    let mode = segmented_mode(&scheme)?;
+    let options = EncodeOptions {
+        mode,
+        layout: PayloadLayout::Segmented,
+        encryption_key: Some(key),
+        ..EncodeOptions::new(frame_duration_ms, strength_percent)
+    };
+    let (encoded, _) = ENGINE
+        .with(|engine| engine.borrow_mut().encode_with_options(&samples, sample_rate, &message, &options))
+        .map_err(|err| JsError::new(&err))?;
+    Ok(encoded)
+}
+
+/// Decode and decrypt a message encoded with [`encode_audio_encrypted`]
+/// (deterministic encryption: see its notes on repeated messages)
+/// 
+/// # Arguments
+/// * `samples` - Audio samples as f32 array (normalized to [-1.0, 1.0])
+/// * `sample_rate` - Sample rate in Hz
+/// * `scheme` - One of [`scheme_names`]
+/// * `key` - The encoder's shared secret
+/// * `strength_percent` - The encoder's strength (default: 15), which QIM's lattice step follows
+/// 
+/// # Returns
+/// Decoded watermark as JSON string, or an error for an unknown scheme or a payload the key does not open
+#[wasm_bindgen]
+pub fn decode_audio_encrypted(
+    samples: Vec<f32>,
+    sample_rate: u32,
+    scheme: String,
+    key: Vec<u8>,
+    strength_percent: u32,
+) -> Result<String, JsError> {
+    let mode: EmbeddingMode = scheme.parse().map_err(|err: String| JsError::new(&err))?;
+    let options = DecodeOptions {
+        mode: Some(mode),
+        encryption_key: Some(key),
+        strength_percent: Some(strength_percent),
+        ..DecodeOptions::default()
+    };
+    let (result, _) = ENGINE.with(|engine| engine.borrow_mut().decode_with_options(&samples, sample_rate, &options));
+    if result.decrypted == Some(false) {
+        return Err(JsError::new("payload could not be decrypted: wrong key or damaged watermark"));
+    }
+    let decoded_result = DecodedResult {
+        message: result.message,
+        raw_bytes: result.raw_bytes,
+        authenticated: result.authenticated,
+    };
+    Ok(serde_json::to_string(&decoded_result).unwrap())
+}
+
+/// The scheme named for a segmented encode: only QIM and phase coding read chunks back reliably
+/// (see `segment`), so any other comes back as an error rather than a mark nobody can read
+fn segmented_mode(scheme: &str) -> Result<EmbeddingMode, JsError> {
+    let mode: EmbeddingMode = scheme.parse().map_err(|err: String| JsError::new(&err))?;
+    match mode {
+        EmbeddingMode::Qim | EmbeddingMode::Phase => Ok(mode),
+        _ => Err(JsError::new(&format!("scheme {scheme} cannot carry a segmented payload; use qim or phase"))),
+    }
+}
+
+/// Encode a message with an authentication tag under a shared secret, with a scheme picked by name
+/// (segmented layout, so the tag fits alongside longer messages; use "qim" or "phase")
+/// 
+/// # Arguments
+/// * `samples` - Audio samples as f32 array (normalized to [-1.0, 1.0])
+/// * `sample_rate` - Sample rate in Hz
+/// * `message` - Message string to encode
+/// * `scheme` - One of [`scheme_names`]
+/// * `key` - Shared secret the decoder checks the tag with
+/// * `frame_duration_ms` - Frame duration in milliseconds (default: 32)
+/// * `strength_percent` - Watermark strength as percentage (default: 15)
+/// 
+/// # Returns
+/// Encoded audio samples as Vec<f32>, or an error for an unknown scheme, one other than "qim" or "phase",
+/// or a message too long to tag
+#[wasm_bindgen]
+pub fn encode_audio_authenticated(
+    samples: Vec<f32>,
+    sample_rate: u32,
+    message: String,
+    scheme: String,
+    key: Vec<u8>,
+    frame_duration_ms: u32,
+    strength_percent: u32,
+) -> Result<Vec<f32>, JsError> {
+    let mode = segmented_mode(&scheme)?;
+    let options = EncodeOptions {
+        mode,
+        layout: PayloadLayout::Segmented,
+        auth_key: Some(key),
+        ..EncodeOptions::new(frame_duration_ms, strength_percent)
+    };
+    let (encoded, _) = ENGINE
+        .with(|engine| engine.borrow_mut().encode_with_options(&samples, sample_rate, &message, &options))
+        .map_err(|err| JsError::new(&err))?;
+    Ok(encoded)
+}
+
+/// Decode a message and check its tag, as encoded with [`encode_audio_authenticated`]
+/// 
+/// # Arguments
+/// * `samples` - Audio samples as f32 array (normalized to [-1.0, 1.0])
+/// * `sample_rate` - Sample rate in Hz
+/// * `scheme` - One of [`scheme_names`]
+/// * `key` - The encoder's shared secret
+/// * `strength_percent` - The encoder's strength (default: 15), which QIM's lattice step follows
+/// 
+/// # Returns
+/// Decoded watermark as JSON string, with `authenticated` false for forged or unsigned marks,
+/// or an error for an unknown scheme
+#[wasm_bindgen]
+pub fn decode_audio_authenticated(
+    samples: Vec<f32>,
+    sample_rate: u32,
+    scheme: String,
+    key: Vec<u8>,
+    strength_percent: u32,
+) -> Result<String, JsError> {
+    let mode: EmbeddingMode = scheme.parse().map_err(|err: String| JsError::new(&err))?;
+    let options = DecodeOptions {
+        mode: Some(mode),
+        auth_key: Some(key),
+        strength_percent: Some(strength_percent),
+        ..DecodeOptions::default()
+    };
+    let (result, _) = ENGINE.with(|engine| engine.borrow_mut().decode_with_options(&samples, sample_rate, &options));
+    let decoded_result = DecodedResult {
+        message: result.message,
+        raw_bytes: result.raw_bytes,
+        authenticated: result.authenticated,
+    };
+    Ok(serde_json::to_string(&decoded_result).unwrap())
+}
+
+/// Per-frame integrity map of a recording marked with the named scheme
+/// (default pilot and interleaver, as the encoders here write them; the layout is read from the header)
+/// 
+/// # Arguments
+/// * `samples` - Audio samples as f32 array (normalized to [-1.0, 1.0])
+/// * `sample_rate` - Sample rate in Hz
+/// * `scheme` - One of [`scheme_names`]
+/// * `strength_percent` - The encoder's strength (default: 15), which QIM's lattice step follows
+/// 
+/// # Returns
+/// JSON string with each frame's status and the flagged time ranges, or an error for an unknown scheme
+/// or one whose frames cannot be checked alone ("multiplicative", "additive")
+#[wasm_bindgen]
+pub fn check_integrity(samples: Vec<f32>, sample_rate: u32, scheme: String, strength_percent: u32) -> Result<String, JsError> {
+    let mode: EmbeddingMode = scheme.parse().map_err(|err: String| JsError::new(&err))?;
+    let options = DecodeOptions {
+        mode: Some(mode),
+        strength_percent: Some(strength_percent),
+        ..DecodeOptions::default()
+    };
+    let report = ENGINE
+        .with(|engine| engine.borrow_mut().integrity_map(&samples, sample_rate, &options))
+        .map_err(|err| JsError::new(&err))?;
+    let status_name = |status: FrameStatus| format!("{status:?}").to_lowercase();
+    let result = IntegrityResult {
+        frame_seconds: report.frames.iter().map(|frame| frame.start_seconds).collect(),
+        frame_status: report.frames.iter().map(|frame| status_name(frame.status)).collect(),
+        spans: report
+            .spans
+            .iter()
+            .map(|span| TamperSpanResult {
+                start_seconds: span.start_seconds,
+                end_seconds: span.end_seconds,
+                status: status_name(span.status),
+            })
+            .collect(),
+        intact_fraction: report.intact_fraction(),
+    };
+    Ok(serde_json::to_string(&result).unwrap())
+}
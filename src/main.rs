@@ -1,9 +1,10 @@
 // Import the standard library's environment module for reading command-line arguments
 use std::env;
 
-// Import modules we defined in separate files
-mod decoder; // Contains all decoding logic
-mod encoder; // Contains all encoding logic
+// Import the library modules
+use msg_encoder::decoder; // Contains all decoding logic
+use msg_encoder::encoder; // Contains all encoding logic
+use msg_encoder::EmbeddingMode; // Built-in watermark schemes, selectable by name
 
 // =============================================================================
 // Entry point - runs encode or decode based on command
@@ -13,22 +14,38 @@ fn main() {
     // Collect all command-line arguments into a vector (first arg is program name)
     let args: Vec<String> = env::args().collect();
 
+    // Optional `--scheme NAME` after the command (defaults to multiplicative)
+    let mode = match args.iter().position(|arg| arg == "--scheme") {
+        Some(idx) => match args.get(idx + 1).map(|name| name.parse::<EmbeddingMode>()) {
+            Some(Ok(mode)) => mode,
+            Some(Err(err)) => {
+                eprintln!("{err}");
+                return;
+            }
+            None => {
+                eprintln!("--scheme needs a name");
+                return;
+            }
+        },
+        None => EmbeddingMode::default(),
+    };
+
     // Match on the first argument to determine what mode we're in
     match args[1].as_str() {
         // If user wants to encode the message
         "encode" => {
-            encoder::encode_sample("hi");
+            encoder::encode_sample_with_scheme("hi", mode.scheme());
         }
 
         // If user wants to decode a watermark
         "decode" => {
             // Decode the watermark from the default path
-            decoder::decode_watermarked_sample(&decoder::default_watermarked_path());
+            decoder::decode_watermarked_sample_with_scheme(decoder::default_watermarked_path(), mode.scheme());
         }
 
         // If user provided an unknown option
         _ => {
-            println!("unknown option"); // Print to stderr
+            eprintln!("unknown option"); // Print to stderr
         }
     }
 }
@@ -0,0 +1,250 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::header::{push_field, Fields};
+
+// =============================================================================
+// Payload packing - fewer bits per character for short messages
+// =============================================================================
+//
+// At 8 kHz a frame has room for only a few raw bytes after the pilot and
+// header, so messages drawn from a restricted character set can be packed
+// more tightly. A packed payload starts with a 2-bit mode, then:
+//
+//   six-bit   63-symbol alphabet (letters, digits, space), 6 bits per char
+//   five-bit  31-symbol alphabet (lower case, space, a little punctuation)
+//   huffman   static code tuned to English text, other bytes escaped
+//   varint    comma-separated unsigned integers as LEB128 bytes
+//
+// and is zero-padded to whole bytes; the header's `FLAG_PACKED` tells the
+// decoder to unpack it. Where the padding is long enough to read as another
+// symbol, the encoder ends the symbols with a terminator (the all-ones code of
+// the alphabets, a dedicated Huffman symbol); a varint never fits in padding.
+//
+// A message outside the chosen mode (a `!` under six-bit, say) is sent as raw
+// bytes without `FLAG_PACKED` rather than refused.
+//
+// Packing comes before encryption and authentication, so those protect the
+// packed bytes and the decoder unpacks last.
+
+/// How the encoder packs a message before embedding it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Packing {
+    /// 8 bits per byte, as is
+    #[default]
+    None,
+    /// [`SIX_BIT_ALPHABET`] at 6 bits per character
+    SixBit,
+    /// [`FIVE_BIT_ALPHABET`] at 5 bits per character
+    FiveBit,
+    /// Static Huffman code for English text
+    Huffman,
+    /// Comma-separated unsigned integers as varints
+    Varint,
+    /// Whichever mode gives the shortest payload (or none, if none is shorter)
+    Smallest,
+}
+
+/// Characters of the six-bit mode; code 63 is the terminator.
+pub const SIX_BIT_ALPHABET: &[u8; 63] = b" abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// Characters of the five-bit mode; code 31 is the terminator.
+pub const FIVE_BIT_ALPHABET: &[u8; 31] = b" abcdefghijklmnopqrstuvwxyz.,?-";
+
+const MODE_BITS: usize = 2;
+const MODES: [Packing; 4] = [Packing::SixBit, Packing::FiveBit, Packing::Huffman, Packing::Varint]; // by code
+
+// Rough frequencies of English prose, per mille with spaces counted; the last two
+// entries weight the escape (any other byte) and terminator symbols.
+const ENGLISH_WEIGHTS: [(u8, u32); 32] = [
+    (b' ', 182), (b'e', 102), (b't', 75), (b'a', 65), (b'o', 62), (b'i', 57), (b'n', 57), (b's', 53),
+    (b'h', 50), (b'r', 50), (b'd', 33), (b'l', 33), (b'u', 23), (b'c', 22), (b'm', 20), (b'w', 19),
+    (b'f', 18), (b'g', 16), (b'y', 16), (b'p', 15), (b'b', 12), (b'v', 8), (b'k', 6), (b'.', 6),
+    (b',', 6), (b'j', 1), (b'x', 1), (b'q', 1), (b'z', 1), (b'\'', 1), (0, 12), (0, 2),
+];
+const ESCAPE: usize = 30;
+const TERMINATOR: usize = 31;
+
+/// Packed payload for `message`, or `None` when packing is off (or, for `Smallest`, gains nothing).
+/// An explicit mode that cannot represent the message also gives `None`, so it goes out unpacked.
+pub fn pack(message: &[u8], packing: Packing) -> Option<Vec<u8>> {
+    match packing {
+        Packing::None => None,
+        Packing::Smallest => MODES
+            .iter()
+            .filter_map(|&mode| pack_with(message, mode))
+            .filter(|packed| packed.len() < message.len())
+            .min_by_key(Vec::len),
+        mode => pack_with(message, mode),
+    }
+}
+
+/// Message bytes of a packed payload, or `None` if it does not unpack cleanly.
+pub fn unpack(payload: &[u8]) -> Option<Vec<u8>> {
+    let bits: Vec<u8> = payload.iter().flat_map(|&byte| (0..8).rev().map(move |shift| (byte >> shift) & 1)).collect();
+    let mut fields = Fields(&bits);
+    if fields.0.len() < MODE_BITS {
+        return None;
+    }
+    match MODES[fields.take(MODE_BITS)] {
+        Packing::SixBit => unpack_alphabet(fields, SIX_BIT_ALPHABET, 6),
+        Packing::FiveBit => unpack_alphabet(fields, FIVE_BIT_ALPHABET, 5),
+        Packing::Huffman => unpack_huffman(fields),
+        _ => unpack_varints(fields),
+    }
+}
+
+fn pack_with(message: &[u8], mode: Packing) -> Option<Vec<u8>> {
+    let mut bits = Vec::new();
+    push_field(&mut bits, MODES.iter().position(|&m| m == mode)?, MODE_BITS);
+    match mode {
+        Packing::SixBit => pack_alphabet(&mut bits, message, SIX_BIT_ALPHABET, 6)?,
+        Packing::FiveBit => pack_alphabet(&mut bits, message, FIVE_BIT_ALPHABET, 5)?,
+        Packing::Huffman => pack_huffman(&mut bits, message),
+        _ => pack_varints(&mut bits, message)?,
+    }
+
+    let bytes = bits
+        .chunks(8)
+        .map(|chunk| chunk.iter().fold(0u8, |acc, &bit| (acc << 1) | bit) << (8 - chunk.len()))
+        .collect();
+    Some(bytes)
+}
+
+/// Bits of zero padding `bits` will get to reach a whole byte.
+fn padding(bits: &[u8]) -> usize {
+    (8 - bits.len() % 8) % 8
+}
+
+fn pack_alphabet(bits: &mut Vec<u8>, message: &[u8], alphabet: &[u8], width: usize) -> Option<()> {
+    for byte in message {
+        push_field(bits, alphabet.iter().position(|c| c == byte)?, width);
+    }
+    if padding(bits) >= width {
+        push_field(bits, alphabet.len(), width); // terminator, so the padding is not read as a character
+    }
+    Some(())
+}
+
+fn unpack_alphabet(mut fields: Fields, alphabet: &[u8], width: usize) -> Option<Vec<u8>> {
+    let mut message = Vec::new();
+    while fields.0.len() >= width {
+        match alphabet.get(fields.take(width)) {
+            Some(&byte) => message.push(byte),
+            None => break, // terminator
+        }
+    }
+    Some(message)
+}
+
+fn pack_huffman(bits: &mut Vec<u8>, message: &[u8]) {
+    let codes = huffman_codes();
+    for &byte in message {
+        match ENGLISH_WEIGHTS[..ESCAPE].iter().position(|&(c, _)| c == byte) {
+            Some(symbol) => push_field(bits, codes[symbol].0, codes[symbol].1),
+            None => {
+                push_field(bits, codes[ESCAPE].0, codes[ESCAPE].1);
+                push_field(bits, byte.into(), 8);
+            }
+        }
+    }
+    let shortest = codes.iter().map(|&(_, len)| len).min().unwrap_or(0);
+    if padding(bits) >= shortest {
+        push_field(bits, codes[TERMINATOR].0, codes[TERMINATOR].1);
+    }
+}
+
+fn unpack_huffman(mut fields: Fields) -> Option<Vec<u8>> {
+    let codes = huffman_codes();
+    let longest = codes.iter().map(|&(_, len)| len).max().unwrap_or(0);
+    let mut message = Vec::new();
+    let (mut code, mut len) = (0, 0);
+    while !fields.0.is_empty() {
+        code = (code << 1) | fields.take(1);
+        len += 1;
+        let Some(symbol) = codes.iter().position(|&c| c == (code, len)) else {
+            if len == longest {
+                return None; // no code starts like this
+            }
+            continue; // not a complete code yet
+        };
+        match symbol {
+            TERMINATOR => break,
+            ESCAPE if fields.0.len() >= 8 => message.push(fields.take(8) as u8),
+            ESCAPE => return None,
+            _ => message.push(ENGLISH_WEIGHTS[symbol].0),
+        }
+        (code, len) = (0, 0);
+    }
+    Some(message)
+}
+
+/// Canonical Huffman `(code, length)` per symbol of `ENGLISH_WEIGHTS`.
+fn huffman_codes() -> Vec<(usize, usize)> {
+    // Merge the two lightest nodes until one is left (ties broken by node index, so the code is fixed)
+    let mut parents: Vec<Option<usize>> = vec![None; ENGLISH_WEIGHTS.len()];
+    let mut heap: BinaryHeap<Reverse<(u32, usize)>> =
+        ENGLISH_WEIGHTS.iter().enumerate().map(|(node, &(_, weight))| Reverse((weight, node))).collect();
+    while let (Some(Reverse((w1, a))), Some(Reverse((w2, b)))) = (heap.pop(), heap.pop()) {
+        let node = parents.len();
+        parents.push(None);
+        parents[a] = Some(node);
+        parents[b] = Some(node);
+        heap.push(Reverse((w1 + w2, node)));
+    }
+    let depth = |mut node: usize| {
+        let mut depth = 0;
+        while let Some(parent) = parents[node] {
+            node = parent;
+            depth += 1;
+        }
+        depth
+    };
+
+    // Canonical codes: shorter first, then by symbol
+    let mut symbols: Vec<(usize, usize)> = (0..ENGLISH_WEIGHTS.len()).map(|symbol| (depth(symbol), symbol)).collect();
+    symbols.sort_unstable();
+    let mut codes = vec![(0, 0); ENGLISH_WEIGHTS.len()];
+    let (mut code, mut previous_len) = (0, symbols[0].0);
+    for (len, symbol) in symbols {
+        code <<= len - previous_len;
+        codes[symbol] = (code, len);
+        code += 1;
+        previous_len = len;
+    }
+    codes
+}
+
+fn pack_varints(bits: &mut Vec<u8>, message: &[u8]) -> Option<()> {
+    for number in std::str::from_utf8(message).ok()?.split(',') {
+        let canonical = number == "0" || !number.starts_with('0');
+        if !canonical || !number.bytes().all(|b| b.is_ascii_digit()) {
+            return None; // would not unpack to the same text
+        }
+        let mut value: u64 = number.parse().ok()?;
+        loop {
+            let group = (value & 0x7f) as usize;
+            value >>= 7;
+            push_field(bits, usize::from(value != 0) << 7 | group, 8);
+            if value == 0 {
+                break;
+            }
+        }
+    }
+    Some(())
+}
+
+fn unpack_varints(mut fields: Fields) -> Option<Vec<u8>> {
+    let mut numbers = Vec::new();
+    let (mut value, mut shift) = (0u64, 0);
+    while fields.0.len() >= 8 {
+        let group = fields.take(8) as u64;
+        value |= (group & 0x7f).checked_shl(shift)?;
+        shift += 7;
+        if group & 0x80 == 0 {
+            numbers.push(value.to_string());
+            (value, shift) = (0, 0);
+        }
+    }
+    (shift == 0 && !numbers.is_empty()).then(|| numbers.join(",").into_bytes())
+}
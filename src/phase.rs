@@ -0,0 +1,62 @@
+use realfft::num_complex::Complex;
+
+use crate::scheme::{self, AnalysisFrame, EmbedFrame, WatermarkScheme, START_BIN};
+
+// =============================================================================
+// Phase coding - carry bits in the phase of the carrier bins
+// =============================================================================
+//
+// Each carrier bin keeps its magnitude and has its phase set to +π/2 (bit 1) or
+// -π/2 (bit 0), i.e. the bin becomes purely imaginary. Magnitude-only processing
+// such as equalisation or gentle compression leaves the sign of the imaginary
+// part intact, and the ear is far less sensitive to these phase changes than to
+// the magnitude steps of the other modes.
+
+// Bins quieter than this (single-sinusoid level, dBFS) are raised to it so they
+// have a phase to carry.
+const PHASE_FLOOR_DB: f32 = -80.0;
+
+/// Rotate each bin from `start_bin` to ±π/2 according to its bit, keeping its magnitude.
+pub fn embed_phase(spectrum: &mut [Complex<f32>], bits: &[u8], start_bin: usize) {
+    let floor = scheme::sinusoid_magnitude(PHASE_FLOOR_DB, spectrum.len());
+    for (&bit, bin) in bits.iter().zip(&mut spectrum[start_bin..]) {
+        let magnitude = bin.norm().max(floor);
+        *bin = Complex::new(0.0, if bit == 1 { magnitude } else { -magnitude });
+    }
+    scheme::real_nyquist(spectrum); // it has no phase to carry a bit, hence the capacity
+}
+
+/// Soft bit per bin in [-1, 1]: the sine of the bin's phase.
+pub fn phase_scores(bins: &[Complex<f32>], scores: &mut Vec<f32>) {
+    scores.clear();
+    scores.extend(bins.iter().map(|bin| {
+        let magnitude = bin.norm();
+        if magnitude > 0.0 {
+            bin.im / magnitude
+        } else {
+            0.0
+        }
+    }));
+}
+
+/// Phase coding: bits in the sign of each carrier bin's imaginary part.
+pub struct PhaseScheme;
+
+impl WatermarkScheme for PhaseScheme {
+    fn id(&self) -> &'static str {
+        "phase"
+    }
+
+    fn capacity(&self, spectrum_len: usize) -> usize {
+        spectrum_len.saturating_sub(START_BIN + 1) // not the Nyquist bin
+    }
+
+    fn embed_frame(&self, frame: &mut EmbedFrame<'_>) {
+        embed_phase(frame.spectrum, frame.bits, START_BIN);
+    }
+
+    fn analyse_frame(&self, frame: &mut AnalysisFrame<'_>, scores: &mut Vec<f32>) {
+        let first = START_BIN.min(frame.spectrum.len());
+        phase_scores(&frame.spectrum[first..], scores);
+    }
+}
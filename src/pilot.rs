@@ -0,0 +1,196 @@
+// =============================================================================
+// Pilots - sync sequences and correlation-based frame detection
+// =============================================================================
+//
+// Every frame's bitstream starts with a pilot the decoder knows, used both to
+// accept the frame and to set its decision threshold. The default is the
+// original eight alternating bits; longer sequences with low off-peak
+// autocorrelation (Barker-13, maximal-length LFSR sequences) make a chance
+// match in unmarked audio far less likely, at the cost of capacity.
+//
+// A frame is accepted when the normalised correlation between its pilot
+// scores and the pilot (both centred, the pilot as ±1) reaches a threshold;
+// its sign gives the frame's polarity. For an unmarked frame with independent
+// Gaussian scores the centred scores point in a uniformly random direction of
+// the (n - 1)-dimensional subspace orthogonal to the all-ones vector, so the
+// correlation ρ has density proportional to (1 - ρ²)^((n - 4) / 2). The
+// threshold is the point where the two-sided tail of that density equals the
+// false-alarm probability asked for.
+//
+// Where a frame is judged on its own (integrity map, timelines, segment
+// fields) the threshold is applied frame by frame, at a rate low enough that
+// unmarked audio is not read as marked: QIM and phase coding clear it on
+// every marked frame even with the 8-bit default pilot. The spectral-contrast
+// schemes need many frames per bit, and few of their frames clear it alone, so
+// the repeated layout's median voting judges the recording instead. Every
+// frame clearing the loose `VOTE_FALSE_ALARM` threshold votes (the medians
+// shrug off the unmarked ones among them, as they did under the old 5-of-8
+// rule). A legacy stream is decoded only if enough of its frames clear the
+// strict threshold on their own that chance (a binomial tail at the
+// false-alarm rate) would explain them no more often than that rate. A
+// version 1 stream may fall short of that test: its voted header must then
+// pass its CRC and name the scheme and frame duration decoded with.
+//
+// The pilot is not signalled: the decoder must be given the encoder's.
+
+/// Default pilot: alternating 0s and 1s give a clear separation between high and low magnitudes.
+pub const PILOT_PATTERN: [u8; 8] = [0, 1, 0, 1, 0, 1, 0, 1];
+
+/// False-alarm probability (per frame, or per recording when frames are voted) unless the decoder asks for another.
+pub const DEFAULT_FALSE_ALARM: f64 = 1e-3;
+
+/// Share of unmarked frames allowed to vote; the recording-level test or the header's CRC keeps the result honest.
+pub const VOTE_FALSE_ALARM: f64 = 0.75;
+
+/// Shortest pilot whose correlation has a usable null distribution.
+pub const MIN_PILOT_BITS: usize = 4;
+
+const BARKER_13: [u8; 13] = [1, 1, 1, 1, 1, 0, 0, 1, 1, 0, 1, 0, 1];
+
+// Primitive polynomials x^d + ... + 1 by degree, as the mask of their lower terms
+const PRIMITIVE_TAPS: [(u32, u32); 8] = [
+    (3, 0b011),
+    (4, 0b0011),
+    (5, 0b0_0101),
+    (6, 0b00_0011),
+    (7, 0b000_0011),
+    (8, 0b0001_1101),
+    (9, 0b0_0001_0001),
+    (10, 0b00_0000_1001),
+];
+
+/// The known sequence at the start of every frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Pilot {
+    /// [`PILOT_PATTERN`]: eight alternating bits
+    #[default]
+    Alternating,
+    /// The 13-bit Barker code
+    Barker13,
+    /// Maximal-length LFSR sequence of `2^degree - 1` bits (degree 3 to 10)
+    MSequence { degree: u32 },
+}
+
+impl Pilot {
+    /// The pilot's bits; `Err` for an m-sequence degree outside 3 to 10.
+    pub fn bits(&self) -> Result<Vec<u8>, String> {
+        match *self {
+            Self::Alternating => Ok(PILOT_PATTERN.to_vec()),
+            Self::Barker13 => Ok(BARKER_13.to_vec()),
+            Self::MSequence { degree } => m_sequence(degree),
+        }
+    }
+
+    /// Number of pilot bits (what [`Pilot::bits`] would return, even for a degree it rejects).
+    pub fn length(&self) -> usize {
+        match *self {
+            Self::Alternating => PILOT_PATTERN.len(),
+            Self::Barker13 => BARKER_13.len(),
+            Self::MSequence { degree } => 1usize.checked_shl(degree).map_or(usize::MAX, |period| period - 1),
+        }
+    }
+}
+
+/// One period of the m-sequence of `degree`, starting from the all-ones state.
+fn m_sequence(degree: u32) -> Result<Vec<u8>, String> {
+    let Some(&(_, taps)) = PRIMITIVE_TAPS.iter().find(|&&(d, _)| d == degree) else {
+        return Err(format!("m-sequence pilots take a degree from 3 to 10, not {degree}"));
+    };
+    let mut window = (1u32 << degree) - 1; // bit j holds a[i + j]
+    Ok((0..(1usize << degree) - 1)
+        .map(|_| {
+            let bit = (window & 1) as u8;
+            let next = (window & taps).count_ones() & 1; // a[i + d] from the recurrence
+            window = (window >> 1) | (next << (degree - 1));
+            bit
+        })
+        .collect())
+}
+
+/// Normalised correlation of `scores` with `pilot` (both centred), in [-1, 1]; negative for flipped polarity.
+/// 0 when there are fewer scores than pilot bits, or too few bits to centre.
+pub fn correlation(scores: &[f32], pilot: &[u8]) -> f32 {
+    if scores.len() < pilot.len() || pilot.len() < 2 {
+        return 0.0; // nothing to correlate
+    }
+    let n = pilot.len() as f32;
+    let score_mean = scores[..pilot.len()].iter().sum::<f32>() / n;
+    let pilot_mean = pilot.iter().map(|&bit| bipolar(bit)).sum::<f32>() / n;
+
+    let (mut cross, mut score_energy, mut pilot_energy) = (0.0f32, 0.0f32, 0.0f32);
+    for (&score, &bit) in scores.iter().zip(pilot) {
+        let (s, p) = (score - score_mean, bipolar(bit) - pilot_mean);
+        cross += s * p;
+        score_energy += s * s;
+        pilot_energy += p * p;
+    }
+    let norm = (score_energy * pilot_energy).sqrt();
+    if norm > 0.0 { cross / norm } else { 0.0 }
+}
+
+/// Probability that an unmarked frame's |correlation| with a `pilot_len`-bit pilot reaches `threshold`.
+/// `Err` for pilots shorter than [`MIN_PILOT_BITS`].
+pub fn false_alarm_probability(pilot_len: usize, threshold: f32) -> Result<f64, String> {
+    if pilot_len < MIN_PILOT_BITS {
+        return Err(format!("pilots need at least {MIN_PILOT_BITS} bits, not {pilot_len}"));
+    }
+    let exponent = (pilot_len as f64 - 4.0) / 2.0;
+    let density = |x: f64| (1.0 - x * x).max(0.0).powf(exponent);
+    let threshold = f64::from(threshold).clamp(0.0, 1.0);
+    Ok(integrate(density, threshold, 1.0) / integrate(density, 0.0, 1.0))
+}
+
+/// Smallest |correlation| an unmarked frame reaches with probability at most `false_alarm`.
+/// `Err` for a false-alarm probability outside (0, 1] or a pilot shorter than [`MIN_PILOT_BITS`].
+pub fn detection_threshold(pilot_len: usize, false_alarm: f64) -> Result<f32, String> {
+    if !(false_alarm > 0.0 && false_alarm <= 1.0) {
+        return Err(format!("false-alarm probability must lie in (0, 1], not {false_alarm}"));
+    }
+    let (mut low, mut high) = (0.0f32, 1.0f32);
+    for _ in 0..40 {
+        let mid = (low + high) / 2.0; // the tail shrinks as the threshold rises
+        if false_alarm_probability(pilot_len, mid)? > false_alarm {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(high)
+}
+
+/// Whether a recording whose frames have pilot correlations `correlations` carries a mark at `false_alarm`:
+/// enough frames clear the per-frame threshold on their own that chance would explain it at most that often.
+/// Settings [`detection_threshold`] rejects detect nothing.
+pub fn recording_detected(correlations: &[f32], pilot_len: usize, false_alarm: f64) -> bool {
+    let Ok(threshold) = detection_threshold(pilot_len, false_alarm) else {
+        return false;
+    };
+    let passed = correlations.iter().filter(|&&c| c.abs() >= threshold).count();
+    passed > 0 && binomial_tail(correlations.len(), passed, false_alarm) <= false_alarm
+}
+
+/// Probability of at least `k` successes in `n` trials of probability `p`.
+fn binomial_tail(n: usize, k: usize, p: f64) -> f64 {
+    if p >= 1.0 {
+        return 1.0;
+    }
+    let mut term = (1.0 - p).powi(n as i32); // P(exactly 0)
+    let mut below = 0.0;
+    for i in 0..k.min(n + 1) {
+        below += term;
+        term *= (n - i) as f64 / (i + 1) as f64 * p / (1.0 - p);
+    }
+    (1.0 - below).max(0.0)
+}
+
+fn bipolar(bit: u8) -> f32 {
+    if bit == 1 { 1.0 } else { -1.0 }
+}
+
+/// Simpson's rule over `[a, b]`.
+fn integrate(f: impl Fn(f64) -> f64, a: f64, b: f64) -> f64 {
+    const STEPS: usize = 512; // even
+    let h = (b - a) / STEPS as f64;
+    let inner: f64 = (1..STEPS).map(|i| f(a + i as f64 * h) * if i % 2 == 1 { 4.0 } else { 2.0 }).sum();
+    (f(a) + inner + f(b)) * h / 3.0
+}
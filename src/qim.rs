@@ -0,0 +1,129 @@
+use realfft::num_complex::Complex;
+
+use crate::encoder::DEFAULT_STRENGTH_PERCENT;
+use crate::energy;
+use crate::scheme::{self, AnalysisFrame, EmbedFrame, WatermarkScheme, START_BIN};
+
+// =============================================================================
+// QIM - dither-modulated quantisation of log magnitudes
+// =============================================================================
+//
+// Each carrier bin's log magnitude is snapped to one of two interleaved lattices
+// (offset by half a step) chosen by the bit. The decoder reads the bit back from
+// whichever lattice is nearer, without estimating anything about the host signal.
+// A bin decodes correctly as long as its log magnitude moves by less than a
+// quarter step between encoder and decoder (1.5 dB at the default strength).
+//
+// The step grows with the strength, so the decoder has to be told the encoder's
+// strength. The dither is keyed on the frame index and repeats every
+// `DITHER_FRAMES` frames; a cropped excerpt only knows its frame index modulo
+// that, so the alignment search tries each phase.
+
+/// Lattice step in dB at [`DEFAULT_STRENGTH_PERCENT`].
+pub const QIM_STEP_DB: f32 = 6.0;
+
+/// Frames after which the dither pattern repeats.
+pub const DITHER_FRAMES: usize = 4;
+
+const STEP_RANGE_DB: (f32, f32) = (2.0, 18.0); // audible well before the top, lost in noise below the bottom
+
+// Bins quieter than this (single-sinusoid level, dBFS) are quantised as if they sat
+// at this level, which lets QIM mark digital silence too.
+const QIM_FLOOR_DB: f32 = -80.0;
+
+// Dither seed, offset by the frame's place in the dither period
+const DITHER_SEED: usize = 0x51_4d;
+
+/// Lattice step in dB for `strength_percent`, proportional to it around [`QIM_STEP_DB`].
+pub fn qim_step_db(strength_percent: u32) -> f32 {
+    (QIM_STEP_DB * strength_percent as f32 / DEFAULT_STRENGTH_PERCENT as f32).clamp(STEP_RANGE_DB.0, STEP_RANGE_DB.1)
+}
+
+fn step_ln(step_db: f32) -> f32 {
+    step_db * std::f32::consts::LN_10 / 20.0
+}
+
+/// Per-bin dither in [0, step) for a frame, shared by encoder and decoder.
+fn dither(frame_index: usize, bin: usize, step: f32) -> f32 {
+    energy::carrier_phase(DITHER_SEED + frame_index % DITHER_FRAMES, bin) / std::f32::consts::TAU * step
+}
+
+fn floor_ln(spectrum_len: usize) -> f32 {
+    scheme::sinusoid_magnitude(QIM_FLOOR_DB, spectrum_len).ln()
+}
+
+/// Nearest point of the lattice for `bit` (dithered, offset by half a step for 1s).
+fn quantise(value: f32, bit: u8, dither: f32, step: f32) -> f32 {
+    let offset = dither + f32::from(bit) * step * 0.5;
+    ((value - offset) / step).round() * step + offset
+}
+
+/// Snap the log magnitude of each bin from `start_bin` onto the lattice of its bit, keeping phase.
+pub fn embed_qim(spectrum: &mut [Complex<f32>], bits: &[u8], start_bin: usize, frame_index: usize, step_db: f32) {
+    let floor = floor_ln(spectrum.len());
+    let step = step_ln(step_db);
+    for (offset, (&bit, bin)) in bits.iter().zip(&mut spectrum[start_bin..]).enumerate() {
+        let idx = start_bin + offset;
+        let magnitude = bin.norm();
+        let value = magnitude.max(f32::MIN_POSITIVE).ln().max(floor);
+        let mut target = quantise(value, bit, dither(frame_index, idx, step), step);
+        if target < floor {
+            target += step; // stay above the floor, where the decoder clamps
+        }
+        let target = target.exp();
+        *bin = if magnitude > 0.0 {
+            *bin * (target / magnitude)
+        } else {
+            Complex::from_polar(target, energy::carrier_phase(frame_index, idx)) // silent bin
+        };
+    }
+    scheme::real_nyquist(spectrum);
+}
+
+/// Soft bit per bin in [-1, 1]: positive when the "1" lattice is nearer.
+/// `magnitudes[i]` is bin `start_bin + i`; `spectrum_len` fixes the silence floor.
+pub fn qim_scores(
+    magnitudes: &[f32],
+    start_bin: usize,
+    spectrum_len: usize,
+    frame_index: usize,
+    step_db: f32,
+    scores: &mut Vec<f32>,
+) {
+    let floor = floor_ln(spectrum_len);
+    let step = step_ln(step_db);
+    scores.clear();
+    scores.extend(magnitudes.iter().enumerate().map(|(offset, &magnitude)| {
+        let value = magnitude.max(f32::MIN_POSITIVE).ln().max(floor);
+        let d = dither(frame_index, start_bin + offset, step);
+        let err0 = (value - quantise(value, 0, d, step)).abs();
+        let err1 = (value - quantise(value, 1, d, step)).abs();
+        (err0 - err1) / (step * 0.5)
+    }));
+}
+
+/// Dither-modulated QIM on log magnitudes.
+pub struct QimScheme;
+
+impl WatermarkScheme for QimScheme {
+    fn id(&self) -> &'static str {
+        "qim"
+    }
+
+    fn capacity(&self, spectrum_len: usize) -> usize {
+        spectrum_len.saturating_sub(START_BIN)
+    }
+
+    fn index_period(&self) -> usize {
+        DITHER_FRAMES
+    }
+
+    fn embed_frame(&self, frame: &mut EmbedFrame<'_>) {
+        embed_qim(frame.spectrum, frame.bits, START_BIN, frame.index, qim_step_db(frame.strength_percent));
+    }
+
+    fn analyse_frame(&self, frame: &mut AnalysisFrame<'_>, scores: &mut Vec<f32>) {
+        let step_db = qim_step_db(frame.strength_percent);
+        qim_scores(frame.magnitudes, START_BIN, frame.spectrum.len(), frame.index, step_db, scores);
+    }
+}
@@ -0,0 +1,150 @@
+use realfft::num_complex::Complex;
+
+use crate::decoder;
+use crate::energy::db_to_amplitude;
+
+// =============================================================================
+// Watermark schemes - one trait, one implementation per embedding method
+// =============================================================================
+//
+// The encoder and decoder own framing, FFTs, energy gating and voting; a
+// scheme only decides how bits go into a frame and how to read them back.
+//
+// Bit layout: a scheme whose capacity covers the pilot and length header
+// carries the bitstream from its first bit in every frame (cut to capacity).
+// A smaller scheme carries `capacity` consecutive bits per frame, cycling
+// through the stream: frame `i` starts at bit `i × capacity mod bits.len()`,
+// and the decoder finds the cycle length by folding frames. With the
+// segmented layout (see `crate::segment`) each frame of a large scheme is
+// handed its own short bitstream instead, laid out the same way.
+
+/// First bin a spectral scheme writes, away from the low frequencies to reduce audibility.
+pub const START_BIN: usize = 48;
+
+/// Where a scheme writes its bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Domain {
+    /// Edit the frame's half spectrum; the encoder handles the FFTs.
+    Spectrum,
+    /// Write the frame's output samples directly.
+    Time,
+}
+
+/// An embedding method the encoder and decoder can be generic over.
+pub trait WatermarkScheme: Sync {
+    /// Short stable name, used by the CLI and WASM layers to pick a scheme.
+    fn id(&self) -> &'static str;
+
+    /// Bits one frame carries, given the frame's half-spectrum length.
+    fn capacity(&self, spectrum_len: usize) -> usize;
+
+    /// Domain `embed_frame` works in.
+    fn domain(&self) -> Domain {
+        Domain::Spectrum
+    }
+
+    /// Whether the decoder Hann-windows each frame before the FFT handed to `analyse_frame`.
+    fn hann_window(&self) -> bool {
+        false
+    }
+
+    /// Frames after which the scheme's keying on the frame index repeats; the alignment search
+    /// tries each phase, since a cropped excerpt does not know its frames' indices.
+    fn index_period(&self) -> usize {
+        1
+    }
+
+    /// Whether one frame's scores read reliably on their own, as the integrity map needs; the
+    /// spectral-contrast schemes only settle a bit once many frames have voted on it.
+    fn reads_single_frames(&self) -> bool {
+        true
+    }
+
+    /// Write this frame's bits (see the layout above).
+    fn embed_frame(&self, frame: &mut EmbedFrame<'_>);
+
+    /// Soft score per carried bit into `scores`, higher meaning "1".
+    fn analyse_frame(&self, frame: &mut AnalysisFrame<'_>, scores: &mut Vec<f32>);
+}
+
+/// One frame handed to [`WatermarkScheme::embed_frame`].
+pub struct EmbedFrame<'a> {
+    pub index: usize,
+    pub sample_rate: u32,
+    pub bits: &'a [u8], // this frame's bitstream (the whole stream unless segmented)
+    pub strength: f32,
+    pub strength_percent: u32, // the setting `strength` was derived from
+    pub spectrum: &'a mut [Complex<f32>], // Domain::Spectrum: half spectrum to edit
+    pub scratch: &'a mut Vec<f32>,        // per-bin working space
+    pub host: &'a [f32],                  // Domain::Time: the whole unmarked signal
+    pub start: usize,                     // Domain::Time: first sample of this frame in `host`
+    pub samples: &'a mut [f32],           // Domain::Time: output samples to write
+}
+
+/// One frame handed to [`WatermarkScheme::analyse_frame`].
+pub struct AnalysisFrame<'a> {
+    pub index: usize,
+    pub sample_rate: u32,
+    pub strength_percent: u32,        // the encoder's strength, as the decoder was told it
+    pub spectrum: &'a [Complex<f32>], // half spectrum (of the windowed frame if the scheme asks)
+    pub magnitudes: &'a [f32],        // |X| from START_BIN
+    pub log_mags: &'a mut Vec<f32>,   // scratch for decoder::spectral_scores_into
+    pub prefix: &'a mut Vec<f64>,     // scratch for decoder::spectral_scores_into
+}
+
+/// Neighbourhood (bins each side) the spectral-contrast score compares against.
+pub const WINDOW_RADIUS: usize = 3;
+
+/// Scale "1" bins up and "0" bins down by `strength`.
+pub struct MultiplicativeScheme;
+
+impl WatermarkScheme for MultiplicativeScheme {
+    fn id(&self) -> &'static str {
+        "multiplicative"
+    }
+
+    fn capacity(&self, spectrum_len: usize) -> usize {
+        spectrum_len.saturating_sub(START_BIN)
+    }
+
+    fn reads_single_frames(&self) -> bool {
+        false
+    }
+
+    fn embed_frame(&self, frame: &mut EmbedFrame<'_>) {
+        let strength = frame.strength;
+        for (&bit, bin) in frame.bits.iter().zip(&mut frame.spectrum[START_BIN..]) {
+            let scale = if bit == 1 {
+                1.0 + strength
+            } else {
+                (1.0 - strength).max(0.0)
+            };
+            bin.re *= scale;
+            bin.im *= scale;
+        }
+    }
+
+    fn analyse_frame(&self, frame: &mut AnalysisFrame<'_>, scores: &mut Vec<f32>) {
+        spectral_contrast(frame, scores);
+    }
+}
+
+/// Bin magnitude of a single sinusoid at `level_db` dBFS, in a half spectrum of `spectrum_len` bins;
+/// the schemes' floors for bins with nothing (or too little) to carry a bit.
+pub fn sinusoid_magnitude(level_db: f32, spectrum_len: usize) -> f32 {
+    let fft_len = (spectrum_len.max(2) - 1) * 2;
+    db_to_amplitude(level_db) * fft_len as f32 / 2.0 // amplitude A puts A·N/2 in its bin
+}
+
+/// Make the last bin of an edited half spectrum real again, keeping its magnitude: the Nyquist bin
+/// of a real signal has no imaginary part, and the inverse FFT would drop whatever was written there.
+pub fn real_nyquist(spectrum: &mut [Complex<f32>]) {
+    if let Some(last) = spectrum.last_mut() {
+        *last = Complex::new(last.norm().copysign(last.re), 0.0);
+    }
+}
+
+/// Log magnitude of each bin against its neighbours (shared with the additive scheme).
+pub(crate) fn spectral_contrast(frame: &mut AnalysisFrame<'_>, scores: &mut Vec<f32>) {
+    decoder::spectral_scores_into(frame.magnitudes, WINDOW_RADIUS, frame.log_mags, frame.prefix, scores);
+}
@@ -0,0 +1,122 @@
+use crate::pilot::PILOT_PATTERN;
+use crate::header::{crc8, parse_correcting, push_field, xor_mask, Fields};
+
+// =============================================================================
+// Segmented payloads - one long bitstream spread over consecutive frames
+// =============================================================================
+//
+// The repeated layout puts the whole bitstream in every frame, so a message
+// longer than one frame's capacity is cut off. The segmented layout splits the
+// header and payload into chunks instead, one chunk per frame:
+//
+//   pilot 8 | index 8 | count 8 | CRC-8 | chunk (capacity - 32 bits)
+//
+// Frame `i` carries segment `i mod count`, so the cycle repeats over the file
+// and the decoder votes each segment across its repeats: capacity grows with
+// duration rather than FFT size. The pilot doubles as the sync pattern, and
+// the index/count field is CRC-checked and whitened like the header so a frame
+// can say where it sits in the cycle on its own.
+//
+// A message too long for the header's length field or for `MAX_SEGMENTS`
+// chunks is not segmented: the encoder sends it whole (in the legacy layout
+// past `MAX_PAYLOAD_BYTES`, if it is plain), and the decoder, finding no
+// segment fields, reads it as it would the repeated layout.
+//
+// Chunks hold arbitrary data, and the spectral-contrast schemes (multiplicative,
+// additive) misread runs of equal bits, so pair this layout with QIM or phase
+// coding, which score every bin on its own.
+
+/// Segment index, segment count and their CRC.
+pub const SEGMENT_FIELD_BITS: usize = 24;
+
+/// Most segments one cycle can have (the count field is 8 bits).
+pub const MAX_SEGMENTS: usize = 255;
+
+/// Smallest chunk worth segmenting for; below it the repeated layout is used.
+pub const MIN_CHUNK_BITS: usize = 8;
+
+const WHITENING: u32 = 0x96_6969; // Thue-Morse complement, unlike the header's mask
+
+/// Where one frame sits in the segment cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SegmentField {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl SegmentField {
+    /// The `SEGMENT_FIELD_BITS` bits written after the pilot.
+    pub fn to_bits(&self) -> Vec<u8> {
+        let mut bits = Vec::with_capacity(SEGMENT_FIELD_BITS);
+        push_field(&mut bits, self.index, 8);
+        push_field(&mut bits, self.count, 8);
+        let crc = crc8(&bits);
+        push_field(&mut bits, crc.into(), 8);
+        xor_mask(&mut bits, WHITENING);
+        bits
+    }
+
+    /// Parse the bits after a frame's pilot, correcting a single flipped bit.
+    pub fn from_bits(bits: &[u8]) -> Option<Self> {
+        let mut bits = bits.get(..SEGMENT_FIELD_BITS)?.to_vec();
+        xor_mask(&mut bits, WHITENING);
+        parse_correcting(&mut bits, Self::parse)
+    }
+
+    /// [`SegmentField::from_bits`] without the correction: every bit must check out.
+    pub fn from_bits_exact(bits: &[u8]) -> Option<Self> {
+        let mut bits = bits.get(..SEGMENT_FIELD_BITS)?.to_vec();
+        xor_mask(&mut bits, WHITENING);
+        Self::parse(&bits)
+    }
+
+    fn parse(bits: &[u8]) -> Option<Self> {
+        let mut fields = Fields(bits);
+        let field = Self {
+            index: fields.take(8),
+            count: fields.take(8),
+        };
+        let crc = fields.take(8) as u8;
+        let valid = crc == crc8(&bits[..SEGMENT_FIELD_BITS - 8]) && field.index < field.count;
+        valid.then_some(field)
+    }
+}
+
+/// Payload bits per frame for a scheme carrying `capacity` bits, if segmenting is worthwhile.
+pub fn chunk_bits(capacity: usize) -> Option<usize> {
+    chunk_bits_with_pilot(capacity, PILOT_PATTERN.len())
+}
+
+/// Payload bits per frame for a scheme carrying `capacity` bits behind a `pilot_len`-bit pilot,
+/// if segmenting is worthwhile.
+pub fn chunk_bits_with_pilot(capacity: usize, pilot_len: usize) -> Option<usize> {
+    capacity
+        .checked_sub(pilot_len.saturating_add(SEGMENT_FIELD_BITS))
+        .filter(|&chunk| chunk >= MIN_CHUNK_BITS)
+}
+
+/// Per-frame bitstreams (default pilot, field, chunk) for `stream` (header + payload).
+pub fn split_stream(stream: &[u8], chunk_bits: usize) -> Vec<Vec<u8>> {
+    split_stream_with_pilot(stream, &PILOT_PATTERN, chunk_bits)
+}
+
+/// Per-frame bitstreams (`pilot`, field, chunk) for `stream` (header + payload).
+/// The last chunk is left short rather than padded; panics past [`MAX_SEGMENTS`] chunks.
+pub fn split_stream_with_pilot(stream: &[u8], pilot: &[u8], chunk_bits: usize) -> Vec<Vec<u8>> {
+    let count = stream.len().div_ceil(chunk_bits).max(1);
+    assert!(
+        count <= MAX_SEGMENTS,
+        "message needs {count} segments of {chunk_bits} bits; at most {MAX_SEGMENTS} fit in a cycle"
+    );
+
+    (0..count)
+        .map(|index| {
+            let chunk = stream.chunks(chunk_bits).nth(index).unwrap_or_default();
+            let mut bits = Vec::with_capacity(pilot.len() + SEGMENT_FIELD_BITS + chunk.len());
+            bits.extend_from_slice(pilot);
+            bits.extend(SegmentField { index, count }.to_bits());
+            bits.extend_from_slice(chunk);
+            bits
+        })
+        .collect()
+}
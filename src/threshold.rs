@@ -0,0 +1,134 @@
+use crate::decoder::pilot_stats_with_pilot;
+
+// =============================================================================
+// Adaptive thresholds - a decision threshold that follows the band
+// =============================================================================
+//
+// The pilot sits in the lowest bins and gives one threshold for the whole
+// frame, but scores drift with frequency (speech is far from flat), so bins
+// far from the pilot are judged against the wrong midpoint. The adaptive stage
+// groups positions by the bin that carries them into regions of
+// `REGION_BINS`, and fits each region's scores with a two-component Gaussian
+// mixture by expectation-maximisation: the "0" and "1" classes, with a shared
+// spread and their own weights. EM starts from the pilot's class means,
+// shifted to the region's level, so the components keep their meaning (and
+// the polarity the pilot found).
+//
+// Each region's threshold is where the two weighted components cross. The
+// thresholds, placed at the region centres and interpolated linearly between
+// them, form a curve over the band, fitted to the scores aggregated over
+// frames. The decoder subtracts the curve's offset from the pilot threshold
+// from those scores and from every frame's scores before the frame votes, so
+// the rest of the pipeline reads flattened scores against the usual single
+// threshold (each frame still votes against its own pilot's).
+// A region whose fit collapses (too few positions, components merging or
+// swapping) keeps the pilot threshold.
+
+/// Bins per fitted region.
+pub const REGION_BINS: usize = 16;
+
+const MIN_REGION_POSITIONS: usize = 8;
+const EM_ITERATIONS: usize = 25;
+const MIN_SEPARATION: f32 = 0.25; // a fit must keep this fraction of the pilot's class separation
+const WEIGHT_RANGE: (f32, f32) = (0.2, 0.8); // class weights, kept off the extremes
+
+/// Decision threshold for each position, carried by bin `bins[i]`; `pilot` leads the scores.
+pub fn threshold_curve(scores: &[f32], bins: &[usize], pilot: &[u8]) -> Vec<f32> {
+    assert_eq!(scores.len(), bins.len(), "one bin per score");
+    let (avg_high, avg_low, pilot_threshold) = pilot_stats_with_pilot(scores, pilot);
+    let regions = bins.iter().max().map_or(0, |&bin| bin / REGION_BINS + 1);
+
+    // (centre bin, threshold) of every region with a usable fit
+    let mut anchors: Vec<(f32, f32)> = Vec::new();
+    let mut members = Vec::new();
+    for region in 0..regions {
+        members.clear();
+        members.extend(scores.iter().zip(bins).filter(|&(_, &bin)| bin / REGION_BINS == region).map(|(&s, _)| s));
+        if let Some(threshold) = fit_region(&members, avg_high, avg_low) {
+            anchors.push(((region * REGION_BINS) as f32 + REGION_BINS as f32 / 2.0, threshold));
+        }
+    }
+    if anchors.is_empty() {
+        return vec![pilot_threshold; scores.len()];
+    }
+
+    bins.iter().map(|&bin| interpolate(&anchors, bin as f32)).collect()
+}
+
+/// The curve's departure from the pilot threshold at each position.
+pub fn curve_offsets(scores: &[f32], bins: &[usize], pilot: &[u8]) -> Vec<f32> {
+    let (_, _, pilot_threshold) = pilot_stats_with_pilot(scores, pilot);
+    threshold_curve(scores, bins, pilot).into_iter().map(|threshold| threshold - pilot_threshold).collect()
+}
+
+/// `scores` with the curve's departure from the pilot threshold taken out.
+pub fn flatten_scores(scores: &[f32], bins: &[usize], pilot: &[u8]) -> Vec<f32> {
+    let offsets = curve_offsets(scores, bins, pilot);
+    scores.iter().zip(&offsets).map(|(&score, &offset)| score - offset).collect()
+}
+
+/// Crossing point of a two-component mixture fitted to one region, or `None` if the fit is unusable.
+fn fit_region(scores: &[f32], avg_high: f32, avg_low: f32) -> Option<f32> {
+    let separation = avg_high - avg_low; // sign carries the polarity
+    if scores.len() < MIN_REGION_POSITIONS || separation.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    // Pilot class means, moved to the region's level
+    let n = scores.len() as f32;
+    let shift = scores.iter().sum::<f32>() / n - (avg_high + avg_low) / 2.0;
+    let (mut mean_one, mut mean_zero) = (avg_high + shift, avg_low + shift);
+    let mut variance = (separation / 2.0).powi(2);
+    let mut weight_one = 0.5f32;
+    let variance_floor = (0.05 * separation).powi(2);
+
+    for _ in 0..EM_ITERATIONS {
+        // E step: responsibility of the "1" component for each score
+        let (mut sum_r, mut sum_rx, mut sum_x) = (0.0f32, 0.0f32, 0.0f32);
+        let mut responsibilities = Vec::with_capacity(scores.len());
+        for &x in scores {
+            let one = weight_one * (-(x - mean_one).powi(2) / (2.0 * variance)).exp();
+            let zero = (1.0 - weight_one) * (-(x - mean_zero).powi(2) / (2.0 * variance)).exp();
+            let r = if one + zero > 0.0 {
+                one / (one + zero)
+            } else {
+                f32::from(u8::from((x - mean_one).abs() < (x - mean_zero).abs())) // both underflowed: nearer mean
+            };
+            responsibilities.push(r);
+            sum_r += r;
+            sum_rx += r * x;
+            sum_x += x;
+        }
+        if sum_r < 1.0 || n - sum_r < 1.0 {
+            return None; // one component has emptied
+        }
+
+        // M step
+        mean_one = sum_rx / sum_r;
+        mean_zero = (sum_x - sum_rx) / (n - sum_r);
+        weight_one = (sum_r / n).clamp(WEIGHT_RANGE.0, WEIGHT_RANGE.1);
+        let spread: f32 = scores
+            .iter()
+            .zip(&responsibilities)
+            .map(|(&x, &r)| r * (x - mean_one).powi(2) + (1.0 - r) * (x - mean_zero).powi(2))
+            .sum();
+        variance = (spread / n).max(variance_floor);
+    }
+
+    let fitted = mean_one - mean_zero;
+    if fitted.signum() != separation.signum() || fitted.abs() < MIN_SEPARATION * separation.abs() {
+        return None; // merged, or swapped against the pilot
+    }
+    // Equal weighted densities: the midpoint, moved towards the rarer class
+    Some((mean_one + mean_zero) / 2.0 + variance * ((1.0 - weight_one) / weight_one).ln() / fitted)
+}
+
+/// Piecewise-linear value at `bin` through `anchors` (sorted by bin), held flat past the ends.
+fn interpolate(anchors: &[(f32, f32)], bin: f32) -> f32 {
+    let after = anchors.partition_point(|&(centre, _)| centre <= bin);
+    match (after.checked_sub(1).map(|i| anchors[i]), anchors.get(after)) {
+        (Some((x0, y0)), Some(&(x1, y1))) => y0 + (y1 - y0) * (bin - x0) / (x1 - x0),
+        (Some((_, y)), None) | (None, Some(&(_, y))) => y,
+        (None, None) => unreachable!("anchors are never empty here"),
+    }
+}
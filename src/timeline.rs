@@ -0,0 +1,249 @@
+use std::ops::Range;
+
+use crate::decoder::{
+    self, bit_llrs_with_pilot, decide_bits_with_pilot, frame_alignment, frame_scores, hard_bits, median, open_payload,
+    payload_message_with_pilot, pilot_stats_with_pilot, read_frames, DecodeOptions, DecodedWatermark, FrameScores, FrameSource,
+    LENGTH_HEADER_BITS,
+};
+use crate::encoder::{self, build_coded_bit_sequence, EncodeOptions, EncodeVisualization, FrameBits, PayloadCoding};
+use crate::engine::Engine;
+use crate::fec;
+use crate::header::{Header, HEADER_BITS};
+use crate::scheme::WatermarkScheme;
+
+// =============================================================================
+// Timelines - payloads that change over the recording
+// =============================================================================
+//
+// A timeline is a list of `(start_seconds, payload)` entries: each payload is
+// embedded from the first frame at or after its start until the next entry
+// takes over (the first entry also covers anything before it). Every frame
+// still carries a complete repeated-layout bitstream, so `options.layout` is
+// not used, and each payload must fit in one frame (with the packing, tags,
+// sealing and FEC asked for, which the decoder undoes as it would for a whole
+// recording); the encoder returns an error otherwise.
+//
+// The decoder reads every frame's payload on its own, groups consecutive
+// frames that agree, drops runs too short to trust (a frame misread here and
+// there), then votes each remaining run as a whole. That needs a scheme that
+// can be read from a single frame (QIM or phase coding); cycling schemes have
+// no per-frame pilot and give an empty timeline.
+
+// Runs of fewer frames than this are treated as misreads and dropped.
+const MIN_RUN_FRAMES: usize = 3;
+
+/// One stretch of the recording and the payload it carries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimelineEntry {
+    pub start_seconds: f32,
+    pub end_seconds: f32,
+    pub message: String,
+    pub raw_bytes: Vec<u8>,
+    pub authenticated: Option<bool>, // as in [`DecodedWatermark`]
+    pub decrypted: Option<bool>,
+}
+
+/// Encode `(start_seconds, payload)` entries with a built-in scheme (`options.mode`).
+/// Errors if there are no entries or one does not fit a frame.
+pub fn encode_timeline(
+    samples: &[f32],
+    sample_rate: u32,
+    payloads: &[(f32, String)],
+    options: &EncodeOptions,
+) -> Result<(Vec<f32>, EncodeVisualization), String> {
+    let mut engine = Engine::new();
+    encode_with_engine(&mut engine, options.mode.scheme(), samples, sample_rate, payloads, options)
+}
+
+/// Encode one payload per `block_seconds`, asking `payload_at` for each block's payload by its start time.
+pub fn encode_timeline_with(
+    samples: &[f32],
+    sample_rate: u32,
+    block_seconds: f32,
+    payload_at: impl FnMut(f32) -> String,
+    options: &EncodeOptions,
+) -> Result<(Vec<f32>, EncodeVisualization), String> {
+    encode_timeline(samples, sample_rate, &block_payloads(samples.len(), sample_rate, block_seconds, payload_at)?, options)
+}
+
+/// Recover the timeline with a built-in scheme (the scheme and frames read from the header unless `options` set them; the gate must match the encoder).
+pub fn decode_timeline(samples: &[f32], sample_rate: u32, options: &DecodeOptions) -> Vec<TimelineEntry> {
+    let mut engine = Engine::new();
+    engine.decode_timeline(samples, sample_rate, options)
+}
+
+/// `(start, payload)` entries for consecutive `block_seconds` blocks covering `len` samples.
+pub(crate) fn block_payloads(
+    len: usize,
+    sample_rate: u32,
+    block_seconds: f32,
+    mut payload_at: impl FnMut(f32) -> String,
+) -> Result<Vec<(f32, String)>, String> {
+    if block_seconds.is_nan() || block_seconds <= 0.0 {
+        return Err(format!("timeline blocks must have a positive length, not {block_seconds} s"));
+    }
+    let duration = len as f32 / sample_rate as f32;
+    let blocks = ((duration / block_seconds).ceil() as usize).max(1);
+    Ok((0..blocks)
+        .map(|block| {
+            let start = block as f32 * block_seconds;
+            (start, payload_at(start))
+        })
+        .collect())
+}
+
+/// Timeline encoder body shared by the free functions and [`Engine`]
+pub(crate) fn encode_with_engine<S: WatermarkScheme + ?Sized>(
+    engine: &mut Engine,
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    payloads: &[(f32, String)],
+    options: &EncodeOptions,
+) -> Result<(Vec<f32>, EncodeVisualization), String> {
+    if payloads.is_empty() {
+        return Err("a timeline needs at least one payload".to_string());
+    }
+    let frame_len = encoder::frame_length_samples(sample_rate, options.frame_duration_ms);
+
+    let capacity = scheme.capacity(frame_len.next_power_of_two().max(2) / 2 + 1);
+    let coding = PayloadCoding::from_options(options);
+
+    let mut entries: Vec<&(f32, String)> = payloads.iter().collect();
+    entries.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let blocks = entries
+        .into_iter()
+        .map(|(start, payload)| {
+            let first_frame = ((start.max(0.0) * sample_rate as f32).ceil() as usize).div_ceil(frame_len);
+            let bits = build_coded_bit_sequence(payload, scheme.id(), options.frame_duration_ms, coding)?;
+            if bits.len() > capacity {
+                return Err(format!(
+                    "timeline payload at {start} s needs {} bits with its coding; a frame carries {capacity}",
+                    bits.len()
+                ));
+            }
+            Ok((first_frame, FrameBits::Whole(bits)))
+        })
+        .collect::<Result<_, String>>()?;
+    encoder::embed_stream(engine, scheme, samples, sample_rate, &FrameBits::Blocks(blocks), options)
+}
+
+/// Timeline decoder body shared by the free function and [`Engine`]
+pub(crate) fn decode_with_engine<S: WatermarkScheme + ?Sized>(
+    engine: &mut Engine,
+    scheme: &S,
+    samples: &[f32],
+    sample_rate: u32,
+    options: &DecodeOptions,
+) -> Vec<TimelineEntry> {
+    let options = &decoder::configure_with_scheme(engine, scheme, samples, sample_rate, options);
+    let frame_len = options.frame_len(sample_rate);
+    let ctx = engine.context(frame_len.next_power_of_two().max(2));
+    let Ok(mut source) = FrameSource::new(scheme, samples, frame_len, sample_rate, options) else {
+        return Vec::new(); // no usable pilot to look for
+    };
+    if scheme.capacity(ctx.buffers.spectrum.len()) < source.pilot.len() + LENGTH_HEADER_BITS {
+        return Vec::new(); // no per-frame pilot to read
+    }
+
+    let (offset, first_index) = if options.search_alignment { frame_alignment(ctx, &source) } else { (0, 0) };
+    source.samples = &samples[offset..];
+    source.first_index = first_index;
+    let reads = read_frames(ctx, &source);
+    let frames = frame_scores(&reads, &source);
+
+    let pilot = &source.pilot;
+    let keys: Vec<Option<Vec<u8>>> =
+        frames.iter().map(|frame| frame.as_ref().and_then(|frame| frame_payload(frame, pilot))).collect();
+    let seconds = |frame: usize| (offset + frame * frame_len).min(samples.len()) as f32 / sample_rate as f32;
+    let mut timeline: Vec<TimelineEntry> = Vec::new();
+    for run in payload_runs(&keys) {
+        let Some(mut decoded) = vote_run(&frames[run.clone()], pilot) else {
+            continue; // the run's frames agree, but not on a readable stream
+        };
+        open_payload(&mut decoded, options);
+        match timeline.last_mut() {
+            Some(last) if last.raw_bytes == decoded.raw_bytes => last.end_seconds = seconds(run.end), // voting joined them
+            _ => timeline.push(TimelineEntry {
+                start_seconds: seconds(run.start),
+                end_seconds: seconds(run.end),
+                message: decoded.message,
+                raw_bytes: decoded.raw_bytes,
+                authenticated: decoded.authenticated,
+                decrypted: decoded.decrypted,
+            }),
+        }
+    }
+    timeline
+}
+
+/// Header and (still coded) payload bits one frame reads on its own, if its header checks out.
+fn frame_payload(frame: &FrameScores, pilot: &[u8]) -> Option<Vec<u8>> {
+    let mut bits = hard_bits(frame.scores.get(pilot.len()..)?, frame.threshold, frame.inverted);
+    let header = Header::from_bits(&bits)?;
+    let end = HEADER_BITS + fec::header_coded_len(&header);
+    (bits.len() >= end).then(|| {
+        bits.truncate(end);
+        bits
+    })
+}
+
+/// Frame ranges over which the per-frame payload stays the same.
+/// Unread frames are skipped, and runs shorter than `MIN_RUN_FRAMES` dropped before neighbours are joined.
+fn payload_runs(keys: &[Option<Vec<u8>>]) -> Vec<Range<usize>> {
+    let mut runs: Vec<(Range<usize>, &Vec<u8>, usize)> = Vec::new(); // frames, payload, frames that read it
+    for (index, key) in keys.iter().enumerate() {
+        let Some(key) = key else {
+            continue;
+        };
+        match runs.last_mut() {
+            Some((range, payload, count)) if *payload == key => {
+                range.end = index + 1;
+                *count += 1;
+            }
+            _ => runs.push((index..index + 1, key, 1)),
+        }
+    }
+    runs.retain(|(_, _, count)| *count >= MIN_RUN_FRAMES);
+
+    let mut joined: Vec<(Range<usize>, &Vec<u8>)> = Vec::new();
+    for (range, key, _) in runs {
+        match joined.last_mut() {
+            Some((last, payload)) if *payload == key => last.end = range.end,
+            _ => joined.push((range, key)),
+        }
+    }
+    joined.into_iter().map(|(range, _)| range).collect()
+}
+
+/// Payload of a run of frames, voted position by position as in the whole-file decoder.
+fn vote_run(frames: &[Option<FrameScores>], pilot: &[u8]) -> Option<DecodedWatermark> {
+    let accepted: Vec<&FrameScores> = frames.iter().flatten().collect();
+    let positions = accepted.iter().map(|frame| frame.scores.len()).min()?;
+
+    let mut scores = Vec::with_capacity(positions);
+    let mut votes = Vec::with_capacity(positions);
+    let mut column = Vec::with_capacity(accepted.len());
+    for position in 0..positions {
+        column.clear();
+        column.extend(accepted.iter().map(|frame| frame.scores[position]));
+        let ones = accepted
+            .iter()
+            .filter(|frame| {
+                let score = frame.scores[position];
+                if frame.inverted { score <= frame.threshold } else { score >= frame.threshold }
+            })
+            .count();
+        scores.push(median(&mut column)?);
+        votes.push(ones as f32 / accepted.len() as f32);
+    }
+
+    let (avg_high, avg_low, threshold) = pilot_stats_with_pilot(&scores, pilot);
+    let inverted_frames = accepted.iter().filter(|frame| frame.inverted).count();
+    let inverted = inverted_frames * 2 >= accepted.len() || avg_high < avg_low;
+    let header = Header::from_bits(&hard_bits(&scores[pilot.len()..], threshold, inverted))?;
+
+    let bits = decide_bits_with_pilot(&scores, &votes, pilot.len(), threshold, avg_high, avg_low, inverted);
+    let llrs = bit_llrs_with_pilot(&scores, pilot, inverted);
+    payload_message_with_pilot(header, pilot.len(), &bits, &llrs)
+}
@@ -0,0 +1,73 @@
+//! Authenticated payloads: a truncated HMAC tag tells marks made with the shared key from forgeries.
+
+mod common;
+
+use common::{generate, paired, Signal};
+use msg_encoder::auth::{tag, verify, TAG_BYTES};
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions, Engine, PayloadLayout};
+
+const KEY: &[u8] = b"label shared secret";
+const MESSAGE: &str = "(c) label 2026";
+
+#[test]
+fn tag_rejects_other_keys_and_altered_payloads() {
+    let genuine = tag(KEY, MESSAGE.as_bytes());
+    assert_eq!(genuine.len(), TAG_BYTES);
+    assert!(verify(KEY, MESSAGE.as_bytes(), &genuine));
+    assert!(!verify(b"another key", MESSAGE.as_bytes(), &genuine));
+    assert!(!verify(KEY, b"(c) label 2027", &genuine));
+    assert!(!verify(KEY, MESSAGE.as_bytes(), &genuine[..TAG_BYTES - 1]));
+}
+
+#[test]
+fn genuine_marks_authenticate() {
+    let mut engine = Engine::new();
+    let audio = generate(Signal::SpeechLike, 16_000, 4.0);
+    for layout in [PayloadLayout::Repeated, PayloadLayout::Segmented] {
+        let (encode, decode) = paired(EncodeOptions {
+            mode: EmbeddingMode::Qim,
+            layout,
+            auth_key: Some(KEY.to_vec()),
+            ..EncodeOptions::default()
+        });
+        let (encoded, _) = engine.encode_with_options(&audio, 16_000, MESSAGE, &encode).unwrap();
+        let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &decode);
+        assert_eq!(decoded.message, MESSAGE, "{layout:?}");
+        assert_eq!(decoded.authenticated, Some(true), "{layout:?}");
+        assert!(decoded.header.is_some_and(|header| header.is_authenticated()));
+    }
+}
+
+#[test]
+fn forged_and_unsigned_marks_do_not_authenticate() {
+    let mut engine = Engine::new();
+    let audio = generate(Signal::PinkNoise, 16_000, 4.0);
+
+    let (unsigned, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        ..EncodeOptions::default()
+    });
+    let decode = DecodeOptions {
+        auth_key: Some(KEY.to_vec()),
+        ..decode
+    };
+    let forger = EncodeOptions {
+        auth_key: Some(b"guessed key".to_vec()),
+        ..unsigned.clone()
+    };
+    let (forged, _) = engine.encode_with_options(&audio, 16_000, MESSAGE, &forger).unwrap();
+    let (decoded, _) = engine.decode_with_options(&forged, 16_000, &decode);
+    assert_eq!(decoded.message, MESSAGE);
+    assert_eq!(decoded.authenticated, Some(false));
+
+    let (encoded, _) = engine.encode_with_options(&audio, 16_000, MESSAGE, &unsigned).unwrap();
+    let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &decode);
+    assert_eq!(decoded.message, MESSAGE);
+    assert_eq!(decoded.authenticated, Some(false));
+
+    // Without a key the tag is still stripped, and nothing is claimed either way
+    let keyless = DecodeOptions { auth_key: None, ..decode };
+    let (decoded, _) = engine.decode_with_options(&forged, 16_000, &keyless);
+    assert_eq!(decoded.message, MESSAGE);
+    assert_eq!(decoded.authenticated, None);
+}
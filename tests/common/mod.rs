@@ -0,0 +1,123 @@
+//! Synthetic test signals and option helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+
+use msg_encoder::{DecodeOptions, EncodeOptions};
+
+/// The kinds of material the round-trip suite exercises.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    WhiteNoise,
+    PinkNoise,
+    SineSweep,
+    SpeechLike,
+    Silence,
+}
+
+pub const SIGNALS: [Signal; 5] = [
+    Signal::WhiteNoise,
+    Signal::PinkNoise,
+    Signal::SineSweep,
+    Signal::SpeechLike,
+    Signal::Silence,
+];
+
+/// Small deterministic xorshift generator, so every run sees the same audio.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform in [-1, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    }
+}
+
+pub fn generate(signal: Signal, sample_rate: u32, seconds: f32) -> Vec<f32> {
+    let len = (sample_rate as f32 * seconds) as usize;
+    match signal {
+        Signal::WhiteNoise => white_noise(len, 0.3),
+        Signal::PinkNoise => pink_noise(len),
+        Signal::SineSweep => sine_sweep(len, sample_rate),
+        Signal::SpeechLike => speech_like(len, sample_rate),
+        Signal::Silence => vec![0.0; len],
+    }
+}
+
+pub fn white_noise(len: usize, amplitude: f32) -> Vec<f32> {
+    let mut rng = Rng::new(0x5eed_0001);
+    (0..len).map(|_| rng.next_f32() * amplitude).collect()
+}
+
+/// Paul Kellet's economy pink filter over white noise.
+pub fn pink_noise(len: usize) -> Vec<f32> {
+    let mut rng = Rng::new(0x5eed_0002);
+    let (mut b0, mut b1, mut b2) = (0.0f32, 0.0f32, 0.0f32);
+    (0..len)
+        .map(|_| {
+            let white = rng.next_f32();
+            b0 = 0.99765 * b0 + white * 0.099_046;
+            b1 = 0.963 * b1 + white * 0.296_516_4;
+            b2 = 0.57 * b2 + white * 1.052_691_3;
+            (b0 + b1 + b2 + white * 0.1848) * 0.08
+        })
+        .collect()
+}
+
+/// Exponential sweep from 100 Hz to 90% of Nyquist.
+pub fn sine_sweep(len: usize, sample_rate: u32) -> Vec<f32> {
+    let f0 = 100.0f32;
+    let f1 = sample_rate as f32 * 0.45;
+    let duration = len as f32 / sample_rate as f32;
+    let k = (f1 / f0).ln() / duration;
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / sample_rate as f32;
+            let phase = 2.0 * PI * f0 * ((k * t).exp() - 1.0) / k;
+            0.3 * phase.sin()
+        })
+        .collect()
+}
+
+/// Low-passed noise under a ~4 Hz syllabic envelope with short pauses.
+pub fn speech_like(len: usize, sample_rate: u32) -> Vec<f32> {
+    let mut rng = Rng::new(0x5eed_0003);
+    let mut lowpassed = 0.0f32;
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / sample_rate as f32;
+            lowpassed = 0.7 * lowpassed + 0.3 * rng.next_f32();
+            let syllable = (2.0 * PI * 4.0 * t).sin().max(0.0);
+            let phrase = if (t % 1.5) < 1.2 { 1.0 } else { 0.05 };
+            0.5 * lowpassed * (0.1 + syllable) * phrase
+        })
+        .collect()
+}
+
+/// `encode` with decoder options naming what the decoder needs the same: the scheme,
+/// layout, interleaver, pilot, energy gate and keys. Adjust either side with struct update syntax.
+pub fn paired(encode: EncodeOptions) -> (EncodeOptions, DecodeOptions) {
+    let decode = DecodeOptions {
+        mode: Some(encode.mode),
+        layout: Some(encode.layout),
+        interleaver: encode.interleaver,
+        pilot: encode.pilot,
+        energy_gate: encode.energy_gate,
+        encryption_key: encode.encryption_key.clone(),
+        auth_key: encode.auth_key.clone(),
+        ..DecodeOptions::default()
+    };
+    (encode, decode)
+}
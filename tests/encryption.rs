@@ -0,0 +1,93 @@
+//! Encrypted payloads: sealed with ChaCha20-Poly1305, readable only with the shared secret.
+
+mod common;
+
+use common::{generate, paired, Signal};
+use msg_encoder::cipher::{open, seal, IV_BYTES, SEAL_BYTES};
+use msg_encoder::header::FLAG_ENCRYPTED;
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions, Engine, Header, PayloadLayout};
+
+const SECRET: &[u8] = b"distribution secret";
+const MESSAGE: &str = "customer 00421337";
+
+/// The tag alone outgrows what one frame has left after the header, so payloads are segmented.
+fn sealed() -> EncodeOptions {
+    EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        layout: PayloadLayout::Segmented,
+        encryption_key: Some(SECRET.to_vec()),
+        ..EncodeOptions::default()
+    }
+}
+
+#[test]
+fn sealed_payload_opens_only_with_its_secret_and_header() {
+    let mut header = Header::new("qim", 32, MESSAGE.len() + SEAL_BYTES);
+    header.flags |= FLAG_ENCRYPTED;
+    let sealed = seal(SECRET, &header, MESSAGE.as_bytes());
+    assert_eq!(sealed.len(), MESSAGE.len() + SEAL_BYTES);
+
+    // Same-length messages get their own IV, so they share no keystream
+    let other = seal(SECRET, &header, b"customer 00421338");
+    assert_ne!(sealed[..IV_BYTES], other[..IV_BYTES]);
+    let keystream = |sealed: &[u8], plaintext: &[u8]| -> Vec<u8> {
+        sealed[IV_BYTES..].iter().zip(plaintext).map(|(c, p)| c ^ p).collect()
+    };
+    assert_ne!(keystream(&sealed, MESSAGE.as_bytes()), keystream(&other, b"customer 00421338"));
+    // The documented trade-off: sealing is deterministic, so a repeated message seals the same way
+    assert_eq!(seal(SECRET, &header, MESSAGE.as_bytes()), sealed);
+
+    assert_eq!(open(SECRET, &header, &sealed).as_deref(), Some(MESSAGE.as_bytes()));
+    assert_eq!(open(b"wrong secret", &header, &sealed), None);
+    let other_header = Header { frame_code: 0, ..header };
+    assert_eq!(open(SECRET, &other_header, &sealed), None);
+    let mut damaged = sealed.clone();
+    damaged[IV_BYTES + 3] ^= 0x10;
+    assert_eq!(open(SECRET, &header, &damaged), None);
+    let mut forged_iv = sealed.clone();
+    forged_iv[0] ^= 0x01;
+    assert_eq!(open(SECRET, &header, &forged_iv), None);
+}
+
+#[test]
+fn encrypted_marks_round_trip_with_the_secret() {
+    let mut engine = Engine::new();
+    for rate in [8000, 16_000] {
+        let audio = generate(Signal::SpeechLike, rate, 4.0);
+        let (encode, decode) = paired(sealed());
+        let (encoded, _) = engine.encode_with_options(&audio, rate, MESSAGE, &encode).unwrap();
+        let (decoded, _) = engine.decode_with_options(&encoded, rate, &decode);
+        assert_eq!(decoded.message, MESSAGE, "{rate} Hz");
+        assert_eq!(decoded.decrypted, Some(true), "{rate} Hz");
+        assert!(decoded.header.is_some_and(|header| header.is_encrypted()));
+    }
+
+    // Encryption and authentication combine: the tag covers the ciphertext
+    let audio = generate(Signal::SpeechLike, 16_000, 4.0);
+    let (encode, decode) = paired(sealed());
+    let encode = EncodeOptions { auth_key: Some(b"tag key".to_vec()), ..encode };
+    let decode = DecodeOptions { auth_key: Some(b"tag key".to_vec()), ..decode };
+    let (encoded, _) = engine.encode_with_options(&audio, 16_000, MESSAGE, &encode).unwrap();
+    let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &decode);
+    assert_eq!(decoded.message, MESSAGE);
+    assert_eq!((decoded.authenticated, decoded.decrypted), (Some(true), Some(true)));
+}
+
+#[test]
+fn encrypted_marks_stay_unreadable_without_the_secret() {
+    let mut engine = Engine::new();
+    let audio = generate(Signal::PinkNoise, 16_000, 4.0);
+    for key in [None, Some(&b"wrong secret"[..])] {
+        let (encode, decode) = paired(sealed());
+        let decode = DecodeOptions {
+            encryption_key: key.map(<[u8]>::to_vec),
+            ..decode
+        };
+        let (encoded, _) = engine.encode_with_options(&audio, 16_000, MESSAGE, &encode).unwrap();
+        let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &decode);
+        assert_eq!(decoded.message, "", "{key:?}");
+        assert_eq!(decoded.decrypted, Some(false), "{key:?}");
+        assert_eq!(decoded.raw_bytes.len(), MESSAGE.len() + SEAL_BYTES);
+        assert!(!decoded.raw_bytes.windows(8).any(|window| MESSAGE.as_bytes().windows(8).any(|m| m == window)));
+    }
+}
@@ -0,0 +1,52 @@
+//! The `Engine` reuses FFT plans and frame buffers: decoding allocates per recording, not per frame.
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use common::{generate, Signal};
+use msg_encoder::Engine;
+
+/// Counts this thread's allocations, so tests running alongside do not disturb the count.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations(run: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    run();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn decoding_allocates_per_recording_not_per_frame() {
+    let rate = 16_000;
+    let mut engine = Engine::new();
+    let mut decode_allocations = |seconds: f32| {
+        let audio = generate(Signal::SpeechLike, rate, seconds);
+        let encoded = engine.encode_audio_samples(&audio, rate, "hello", 32, 15);
+        engine.decode_audio_samples(&encoded, rate); // plans and buffers for this size
+        allocations(|| assert_eq!(engine.decode_audio_samples(&encoded, rate).message, "hello"))
+    };
+
+    // Four times the frames: a per-frame allocation would add hundreds
+    let (short, long) = (decode_allocations(2.0), decode_allocations(8.0));
+    assert!(long < short + 20, "{short} allocations for 2 s, {long} for 8 s");
+}
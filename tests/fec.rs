@@ -0,0 +1,109 @@
+//! Convolutional coding: punctured rates, soft Viterbi decoding, and coded payloads end to end.
+
+mod common;
+
+use common::{generate, paired, Rng, Signal};
+use msg_encoder::decoder::decode_audio_samples_with_options;
+use msg_encoder::encoder::encode_audio_samples_with_options;
+use msg_encoder::fec::{coded_len, decode, encode};
+use msg_encoder::{EmbeddingMode, EncodeOptions, Fec, PayloadLayout};
+
+const CODES: [Fec; 3] = [Fec::Half, Fec::TwoThirds, Fec::ThreeQuarters];
+
+fn random_bits(rng: &mut Rng, len: usize) -> Vec<u8> {
+    (0..len).map(|_| (rng.next_u64() & 1) as u8).collect()
+}
+
+/// Confident LLRs for `bits`
+fn llrs(bits: &[u8]) -> Vec<f32> {
+    bits.iter().map(|&bit| if bit == 1 { 4.0 } else { -4.0 }).collect()
+}
+
+#[test]
+fn punctured_rates_round_trip() {
+    let mut rng = Rng::new(48);
+    for bytes in [1, 5, 32] {
+        let bits = random_bits(&mut rng, bytes * 8);
+        assert_eq!(encode(&bits, Fec::None), bits);
+        for (code, rate) in CODES.into_iter().zip([1.0 / 2.0, 2.0 / 3.0, 3.0 / 4.0]) {
+            let coded = encode(&bits, code);
+            assert_eq!(coded.len(), coded_len(bytes, code), "{code:?}");
+            assert_eq!(coded.len() % 8, 0);
+            let expected = (bits.len() + 6) as f32 / rate;
+            assert!((coded.len() as f32 - expected).abs() < 8.0, "{code:?}: {} coded bits", coded.len());
+            assert_eq!(decode(&llrs(&coded), code, bits.len()), bits, "{code:?}");
+        }
+    }
+}
+
+#[test]
+fn soft_viterbi_corrects_scattered_and_doubtful_errors() {
+    let mut rng = Rng::new(7);
+    let bits = random_bits(&mut rng, 64);
+    // Punctured rates keep less redundancy, so their errors must be spread further apart
+    for (code, spacing) in CODES.into_iter().zip([12, 24, 24]) {
+        let coded = encode(&bits, code);
+        let mut received = llrs(&coded);
+
+        // One bit per `spacing` is flipped outright, and one (offset) read with little confidence the wrong way
+        for index in (0..received.len()).step_by(spacing) {
+            received[index] = -received[index];
+        }
+        for index in (spacing / 2..received.len()).step_by(spacing) {
+            received[index] = -received[index] * 0.1;
+        }
+        assert_eq!(decode(&received, code, bits.len()), bits, "{code:?}");
+
+        // Doubtful errors alone never beat the confident majority
+        let mut doubtful = llrs(&coded);
+        for index in (3..doubtful.len()).step_by(5) {
+            doubtful[index] = -doubtful[index] * 0.05;
+        }
+        assert_eq!(decode(&doubtful, code, bits.len()), bits, "{code:?}");
+    }
+}
+
+#[test]
+fn coded_payloads_round_trip_and_name_their_code() {
+    let audio = generate(Signal::SpeechLike, 16_000, 4.0);
+    for (mode, layout, message) in [
+        (EmbeddingMode::Qim, PayloadLayout::Repeated, "fec on"),
+        (EmbeddingMode::Phase, PayloadLayout::Repeated, "fec"),
+        (EmbeddingMode::Qim, PayloadLayout::Segmented, "a longer message over segments"),
+    ] {
+        for code in CODES {
+            let (encode, decode) = paired(EncodeOptions {
+                mode,
+                layout,
+                fec: code,
+                ..EncodeOptions::default()
+            });
+            let (encoded, _) = encode_audio_samples_with_options(&audio, 16_000, message, &encode).unwrap();
+            let (decoded, _) = decode_audio_samples_with_options(&encoded, 16_000, &decode);
+            assert_eq!(decoded.message, message, "{mode:?} {layout:?} {code:?}");
+            assert_eq!(decoded.header.map(|header| header.fec), Some(code.code()));
+        }
+    }
+}
+
+#[test]
+fn coding_recovers_payloads_that_noise_corrupts() {
+    let message = "soft";
+    let audio = generate(Signal::PinkNoise, 16_000, 4.0);
+    let mut rng = Rng::new(47);
+    let noise: Vec<f32> = (0..audio.len()).map(|_| 0.035 * rng.next_f32()).collect();
+
+    let mut messages = Vec::new();
+    for code in [Fec::None, Fec::Half] {
+        let (encode, decode) = paired(EncodeOptions {
+            mode: EmbeddingMode::Qim,
+            fec: code,
+            ..EncodeOptions::default()
+        });
+        let (encoded, _) = encode_audio_samples_with_options(&audio, 16_000, message, &encode).unwrap();
+        let noisy: Vec<f32> = encoded.iter().zip(&noise).map(|(&s, &n)| s + n).collect();
+        messages.push(decode_audio_samples_with_options(&noisy, 16_000, &decode).0.message);
+    }
+    assert_ne!(messages[0], message, "the uncoded payload should be damaged at this noise level");
+    assert_eq!(messages[1], message);
+}
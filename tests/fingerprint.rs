@@ -0,0 +1,61 @@
+//! Tardos fingerprinting: per-recipient codewords, collusion attacks on the marked copies, and the accuser.
+
+mod common;
+
+use common::{generate, paired, Signal};
+use msg_encoder::fingerprint::{
+    code_length, codeword_message, extract_fingerprint, message_codeword, simulate_collusion,
+};
+use msg_encoder::{CollusionAttack, EmbeddingMode, EncodeOptions, PayloadLayout, TardosCode};
+
+const RATE: u32 = 16_000;
+const USERS: usize = 24;
+const COLLUDERS: [usize; 3] = [4, 11, 19];
+
+#[test]
+fn code_is_reproducible_and_biased_within_the_cutoff() {
+    let length = code_length(3, 0.01);
+    let code = TardosCode::new(USERS, length, 3, 7);
+    assert_eq!(code, TardosCode::new(USERS, length, 3, 7));
+    assert_ne!(code, TardosCode::new(USERS, length, 3, 8));
+
+    let cutoff = 1.0 / 900.0;
+    assert!(code.probabilities().iter().all(|&p| (cutoff..=1.0 - cutoff).contains(&p)));
+    for user in 0..USERS {
+        let codeword = code.codeword(user);
+        assert_eq!(codeword.len(), length);
+        assert_eq!(message_codeword(codeword_message(codeword).as_bytes(), length), codeword);
+    }
+}
+
+#[test]
+fn accuser_ranks_an_unaltered_copy_first() {
+    let code = TardosCode::new(USERS, code_length(3, 0.01), 3, 1);
+    for user in 0..USERS {
+        let suspects = code.accuse(code.codeword(user));
+        assert_eq!(suspects[0].user, user);
+        assert!(suspects[1].score < suspects[0].score);
+    }
+}
+
+#[test]
+fn collusion_attacks_are_traced_to_a_colluder() {
+    let length = code_length(3, 0.01);
+    let code = TardosCode::new(USERS, length, 3, 42);
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        layout: PayloadLayout::Segmented,
+        ..EncodeOptions::default()
+    });
+    let audio = generate(Signal::SpeechLike, RATE, 6.0);
+
+    for attack in [CollusionAttack::Average, CollusionAttack::Interleave { block_samples: 512 }] {
+        let leaked = simulate_collusion(&audio, RATE, &code, &COLLUDERS, attack, &encode).unwrap();
+        let bits = extract_fingerprint(&leaked, RATE, length, &decode);
+        let suspects = code.accuse(&bits);
+
+        assert!(COLLUDERS.contains(&suspects[0].user), "{attack:?}: {:?}", &suspects[..4]);
+        let innocent_best = suspects.iter().find(|s| !COLLUDERS.contains(&s.user)).unwrap().score;
+        assert!(suspects[0].score > innocent_best * 1.5, "{attack:?}: {:?}", &suspects[..4]);
+    }
+}
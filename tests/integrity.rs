@@ -0,0 +1,140 @@
+//! The per-frame integrity map: edits to a marked recording show up as flagged time ranges.
+
+mod common;
+
+use common::{generate, paired, Signal};
+use msg_encoder::integrity::IntegrityReport;
+use msg_encoder::{
+    DecodeOptions, EmbeddingMode, EncodeOptions, Engine, EnergyGate, FrameStatus, PayloadLayout, QuietFramePolicy,
+};
+
+const RATE: u32 = 16_000;
+const SECONDS: f32 = 6.0;
+
+fn sample_at(seconds: f32) -> usize {
+    (seconds * RATE as f32) as usize
+}
+
+/// Statuses of the frames lying wholly inside `start..end` seconds.
+fn statuses_within(report: &IntegrityReport, start: f32, end: f32) -> Vec<FrameStatus> {
+    report
+        .frames
+        .iter()
+        .filter(|frame| frame.start_seconds >= start && frame.end_seconds <= end)
+        .map(|frame| frame.status)
+        .collect()
+}
+
+#[test]
+fn unedited_recording_is_intact() {
+    let mut engine = Engine::new();
+    for mode in [EmbeddingMode::Qim, EmbeddingMode::Phase] {
+        for layout in [PayloadLayout::Repeated, PayloadLayout::Segmented] {
+            let (encode, decode) = paired(EncodeOptions {
+                mode,
+                layout,
+                ..EncodeOptions::default()
+            });
+            let audio = generate(Signal::SpeechLike, RATE, SECONDS);
+            let (encoded, _) = engine.encode_with_options(&audio, RATE, "hello", &encode).unwrap();
+
+            let report = engine.integrity_map(&encoded, RATE, &decode).unwrap();
+            assert_eq!(report.frames.len(), encoded.len() / sample_at(0.032));
+            assert_eq!(report.intact_fraction(), 1.0, "{mode:?} {layout:?}");
+            assert!(report.spans.is_empty());
+
+            // Scheme and layout left to the header: segment fields are checked as such
+            let report = engine.integrity_map(&encoded, RATE, &DecodeOptions::default()).unwrap();
+            assert_eq!(report.intact_fraction(), 1.0, "{mode:?} {layout:?} from the header");
+
+            // The unmarked original fails almost everywhere
+            let report = engine.integrity_map(&audio, RATE, &decode).unwrap();
+            assert!(report.intact_fraction() < 0.05, "{mode:?} {layout:?}");
+        }
+    }
+}
+
+#[test]
+fn splice_and_mute_are_localised() {
+    let mut engine = Engine::new();
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        layout: PayloadLayout::Segmented,
+        ..EncodeOptions::default()
+    });
+    let audio = generate(Signal::PinkNoise, RATE, SECONDS);
+    let (mut edited, _) = engine.encode_with_options(&audio, RATE, "hello", &encode).unwrap();
+
+    // Replace 2.0-2.5 s with the unmarked original and mute 4.0-4.3 s
+    let edits = [(2.0, 2.5), (4.0, 4.3)];
+    edited[sample_at(2.0)..sample_at(2.5)].copy_from_slice(&audio[sample_at(2.0)..sample_at(2.5)]);
+    edited[sample_at(4.0)..sample_at(4.3)].fill(0.0);
+
+    let report = engine.integrity_map(&edited, RATE, &decode).unwrap();
+    for &(start, end) in &edits {
+        let inside = statuses_within(&report, start, end);
+        assert!(!inside.is_empty());
+        assert!(inside.iter().all(|&status| status != FrameStatus::Intact), "{start}-{end} s");
+    }
+
+    // Everything flagged lies within a frame of an edit
+    let frame = 0.032;
+    for span in &report.spans {
+        let near_edit = edits
+            .iter()
+            .any(|&(start, end)| span.start_seconds >= start - frame && span.end_seconds <= end + frame);
+        assert!(near_edit, "unexpected span {span:?}");
+    }
+    let untouched = [(0.0, 2.0 - frame), (2.5 + frame, 4.0 - frame), (4.3 + frame, SECONDS)];
+    for (start, end) in untouched {
+        assert!(statuses_within(&report, start, end).iter().all(|&status| status == FrameStatus::Intact));
+    }
+}
+
+#[test]
+fn gated_quiet_frames_are_not_flagged() {
+    let mut engine = Engine::new();
+    let gate = EnergyGate::new(QuietFramePolicy::Skip);
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Phase,
+        energy_gate: Some(gate),
+        ..EncodeOptions::default()
+    });
+
+    // A second of digital silence in the middle of the recording
+    let mut audio = generate(Signal::PinkNoise, RATE, SECONDS);
+    audio[sample_at(2.0)..sample_at(3.0)].fill(0.0);
+    let (encoded, _) = engine.encode_with_options(&audio, RATE, "hello", &encode).unwrap();
+
+    let report = engine.integrity_map(&encoded, RATE, &decode).unwrap();
+    assert!(report.spans.is_empty(), "{:?}", report.spans);
+    assert!(statuses_within(&report, 2.0, 3.0).iter().all(|&status| status == FrameStatus::Quiet));
+    assert_eq!(report.intact_fraction(), 1.0);
+}
+
+#[test]
+fn cycling_schemes_have_no_map() {
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Echo,
+        ..EncodeOptions::default()
+    });
+    let mut engine = Engine::new();
+    let audio = generate(Signal::PinkNoise, RATE, 1.0);
+    let (encoded, _) = engine.encode_with_options(&audio, RATE, "hi", &encode).unwrap();
+    assert_eq!(engine.integrity_map(&encoded, RATE, &decode), Ok(IntegrityReport::default()));
+}
+
+#[test]
+fn contrast_schemes_are_refused() {
+    let mut engine = Engine::new();
+    for mode in [EmbeddingMode::Multiplicative, EmbeddingMode::Additive] {
+        let (encode, decode) = paired(EncodeOptions {
+            mode,
+            ..EncodeOptions::default()
+        });
+        let audio = generate(Signal::PinkNoise, RATE, 1.0);
+        let (encoded, _) = engine.encode_with_options(&audio, RATE, "hi", &encode).unwrap();
+        let refused = engine.integrity_map(&encoded, RATE, &decode);
+        assert!(refused.is_err_and(|err| err.contains("one at a time")), "{mode:?}");
+    }
+}
@@ -0,0 +1,121 @@
+//! Bit interleaving: each frame's bits are permuted over the band and put back in order by the decoder.
+
+mod common;
+
+use common::{generate, paired, Signal};
+use msg_encoder::interleave::{deinterleave_into, interleave};
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions, Engine, Interleaver, PayloadLayout};
+
+const INTERLEAVERS: [Interleaver; 3] = [
+    Interleaver::Block { rows: 8 },
+    Interleaver::Block { rows: 13 },
+    Interleaver::Random { seed: 0x1eaf },
+];
+
+#[test]
+fn orders_are_permutations_that_spread_neighbours() {
+    for capacity in [81, 160, 209] {
+        for interleaver in INTERLEAVERS {
+            let order = interleaver.order(capacity).unwrap().unwrap();
+            let mut sorted = order.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, (0..capacity).collect::<Vec<_>>(), "{interleaver:?} over {capacity}");
+
+            // Neighbouring bins mostly carry bits far apart in the stream
+            let close = order.windows(2).filter(|pair| pair[0].abs_diff(pair[1]) < 4).count();
+            assert!(close * 10 < capacity, "{interleaver:?} over {capacity}: {close} close pairs");
+
+            let bits: Vec<u8> = (0..capacity).map(|i| (i * 7 % 5 % 2) as u8).collect();
+            let scores: Vec<f32> = interleave(&bits, &order).iter().map(|&bit| f32::from(bit)).collect();
+            let mut restored = Vec::new();
+            deinterleave_into(&scores, &order, &mut restored);
+            assert_eq!(restored, bits.iter().map(|&bit| f32::from(bit)).collect::<Vec<_>>());
+        }
+    }
+    assert_eq!(Interleaver::None.order(81), Ok(None));
+}
+
+#[test]
+fn frames_too_small_for_the_pilot_are_not_interleaved() {
+    // 81 bits hold the default pilot and length field, not a 127-bit m-sequence pilot
+    for interleaver in INTERLEAVERS {
+        assert!(interleaver.frame_order(81, 8).unwrap().is_some(), "{interleaver:?}");
+        assert_eq!(interleaver.frame_order(81, 127), Ok(None), "{interleaver:?}");
+    }
+}
+
+#[test]
+fn block_interleavers_without_rows_are_errors() {
+    let empty = Interleaver::Block { rows: 0 };
+    assert!(empty.check().is_err());
+    assert!(empty.order(81).is_err());
+    assert!(empty.frame_order(81, 8).is_err());
+
+    // Encoding refuses it; decoding finds nothing rather than panicking
+    let mut engine = Engine::new();
+    let audio = generate(Signal::SpeechLike, 16_000, 1.0);
+    let (encode, decode) = paired(EncodeOptions { mode: EmbeddingMode::Qim, ..EncodeOptions::default() });
+    let refused = EncodeOptions { interleaver: empty, ..encode.clone() };
+    assert!(engine.encode_with_options(&audio, 16_000, "hi", &refused).is_err());
+    let (encoded, _) = engine.encode_with_options(&audio, 16_000, "hi", &encode).unwrap();
+    let decode = DecodeOptions { interleaver: empty, ..decode };
+    let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &decode);
+    assert_eq!(decoded.message, "");
+    assert!(engine.integrity_map(&encoded, 16_000, &decode).is_err());
+}
+
+#[test]
+fn interleaved_marks_round_trip() {
+    let mut engine = Engine::new();
+    let audio = generate(Signal::SpeechLike, 16_000, 6.0);
+    for mode in [EmbeddingMode::Qim, EmbeddingMode::Phase, EmbeddingMode::Echo] {
+        for interleaver in INTERLEAVERS {
+            let (encode, decode) = paired(EncodeOptions {
+                mode,
+                interleaver,
+                ..EncodeOptions::default()
+            });
+            let (encoded, _) = engine.encode_with_options(&audio, 16_000, "spread", &encode).unwrap();
+            let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &decode);
+            assert_eq!(decoded.message, "spread", "{mode:?} {interleaver:?}");
+        }
+    }
+}
+
+#[test]
+fn interleaved_segments_round_trip() {
+    let mut engine = Engine::new();
+    let message = "Every segment is permuted over the band on its own.";
+    for rate in [8000, 16_000] {
+        let (encode, decode) = paired(EncodeOptions {
+            mode: EmbeddingMode::Qim,
+            layout: PayloadLayout::Segmented,
+            interleaver: Interleaver::Random { seed: 7 },
+            ..EncodeOptions::default()
+        });
+        let audio = generate(Signal::PinkNoise, rate, 6.0);
+        let (encoded, _) = engine.encode_with_options(&audio, rate, message, &encode).unwrap();
+        let (decoded, _) = engine.decode_with_options(&encoded, rate, &decode);
+        assert_eq!(decoded.message, message, "{rate} Hz");
+    }
+}
+
+#[test]
+fn decoder_needs_the_encoder_interleaver() {
+    let mut engine = Engine::new();
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        interleaver: Interleaver::Random { seed: 1 },
+        ..EncodeOptions::default()
+    });
+    let audio = generate(Signal::WhiteNoise, 16_000, 3.0);
+    let (encoded, _) = engine.encode_with_options(&audio, 16_000, "spread", &encode).unwrap();
+
+    // Without the permutation the pilot bins hold noise, so nothing is detected
+    let plain = DecodeOptions { interleaver: Interleaver::None, ..decode.clone() };
+    let (misread, _) = engine.decode_with_options(&encoded, 16_000, &plain);
+    assert_eq!(misread.message, "");
+
+    let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &decode);
+    assert_eq!(decoded.message, "spread");
+}
@@ -0,0 +1,86 @@
+//! Soft decisions: per-bit log-likelihood ratios fitted on the pilot.
+
+mod common;
+
+use common::{generate, paired, Rng, Signal};
+use msg_encoder::encoder::encode_audio_samples_with_options;
+use msg_encoder::decoder::{self, bit_llrs, hard_bits, pilot_stats, LLR_LIMIT};
+use msg_encoder::header::HEADER_BITS;
+use msg_encoder::{EmbeddingMode, EncodeOptions, PayloadLayout, PILOT_PATTERN};
+
+#[test]
+fn llrs_follow_the_pilot_threshold_and_polarity() {
+    let mut scores: Vec<f32> = PILOT_PATTERN.iter().map(|&bit| if bit == 1 { 1.1 } else { -0.9 }).collect();
+    scores[1] = 0.9; // some spread within each class
+    scores[2] = -1.1;
+    scores.extend([2.0, 0.5, 0.15, 0.05, -0.05, -0.5, -2.0]);
+
+    let (_, _, threshold) = pilot_stats(&scores);
+    for inverted in [false, true] {
+        let llrs = bit_llrs(&scores, inverted);
+        let hard = hard_bits(&scores, threshold, inverted);
+        for ((&llr, &bit), &score) in llrs.iter().zip(&hard).zip(&scores) {
+            assert_eq!(u8::from(llr > 0.0), bit, "score {score} (inverted: {inverted}) gave LLR {llr}");
+        }
+
+        // Farther from the threshold means more confident, up to the cap
+        let magnitude = |score: f32| bit_llrs(&[&scores[..PILOT_PATTERN.len()], &[score]].concat(), inverted)[8].abs();
+        assert!(magnitude(0.5) > magnitude(0.15));
+        assert!(magnitude(100.0) <= LLR_LIMIT);
+    }
+}
+
+#[test]
+fn a_flat_pilot_gives_no_confidence() {
+    let llrs = bit_llrs(&[0.3; 24], false);
+    assert_eq!(llrs, vec![0.0; 24]);
+    assert!(bit_llrs(&[1.0, 0.0], false).is_empty());
+}
+
+/// Mean |LLR| over the pilot, header and a `message_len`-byte payload (the rest of the frame is unmarked).
+fn mean_confidence(llrs: &[f32], message_len: usize) -> f32 {
+    let used = PILOT_PATTERN.len() + HEADER_BITS + message_len * 8;
+    llrs[..used].iter().map(|llr| llr.abs()).sum::<f32>() / used as f32
+}
+
+#[test]
+fn decoded_llrs_agree_with_the_decided_bits() {
+    let message = "soft";
+    let used = PILOT_PATTERN.len() + HEADER_BITS + message.len() * 8;
+    for layout in [PayloadLayout::Repeated, PayloadLayout::Segmented] {
+        let (encode, decode) = paired(EncodeOptions {
+            mode: EmbeddingMode::Qim,
+            layout,
+            ..EncodeOptions::default()
+        });
+        let audio = generate(Signal::PinkNoise, 16_000, 4.0);
+        let (encoded, _) = encode_audio_samples_with_options(&audio, 16_000, message, &encode).unwrap();
+
+        let (decoded, viz) = decoder::decode_audio_samples_with_options(&encoded, 16_000, &decode);
+        assert_eq!(decoded.message, message, "{layout:?}");
+        assert_eq!(viz.llrs.len(), viz.scores.len());
+        let soft: Vec<u8> = viz.llrs[..used].iter().map(|&llr| u8::from(llr > 0.0)).collect();
+        assert_eq!(soft, viz.bit_sequence[..used], "{layout:?}: LLR signs should match the decided bits");
+    }
+}
+
+#[test]
+fn noise_lowers_confidence() {
+    let message = "soft";
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        ..EncodeOptions::default()
+    });
+    let audio = generate(Signal::PinkNoise, 16_000, 4.0);
+    let (encoded, _) = encode_audio_samples_with_options(&audio, 16_000, message, &encode).unwrap();
+
+    let mut confidence = Vec::new();
+    for amplitude in [0.0, 0.01, 0.03] {
+        let mut rng = Rng::new(47);
+        let noisy: Vec<f32> = encoded.iter().map(|&s| s + amplitude * rng.next_f32()).collect();
+        let (decoded, viz) = decoder::decode_audio_samples_with_options(&noisy, 16_000, &decode);
+        assert_eq!(decoded.message, message, "noise amplitude {amplitude}");
+        confidence.push(mean_confidence(&viz.llrs, message.len()));
+    }
+    assert!(confidence.windows(2).all(|pair| pair[1] < pair[0]), "confidence by noise level: {confidence:?}");
+}
@@ -0,0 +1,104 @@
+//! Payload packing: restricted alphabets, static Huffman and varints fit more text into a frame.
+
+mod common;
+
+use common::{generate, Signal};
+use msg_encoder::packing::{pack, unpack, FIVE_BIT_ALPHABET, SIX_BIT_ALPHABET};
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions, Engine, Packing};
+use proptest::prelude::*;
+
+fn round_trip(message: &str, packing: Packing) -> Option<String> {
+    let packed = pack(message.as_bytes(), packing)?;
+    unpack(&packed).map(|bytes| String::from_utf8(bytes).unwrap())
+}
+
+fn alphabet_text(alphabet: &'static [u8], max_len: usize) -> impl Strategy<Value = String> {
+    proptest::collection::vec(proptest::sample::select(alphabet), 0..max_len)
+        .prop_map(|bytes| String::from_utf8(bytes).unwrap())
+}
+
+proptest! {
+    #[test]
+    fn alphabets_round_trip(six in alphabet_text(SIX_BIT_ALPHABET, 40), five in alphabet_text(FIVE_BIT_ALPHABET, 40)) {
+        prop_assert_eq!(round_trip(&six, Packing::SixBit), Some(six.clone()));
+        prop_assert_eq!(round_trip(&five, Packing::FiveBit), Some(five.clone()));
+    }
+
+    #[test]
+    fn huffman_round_trips_any_text(message in "\\PC{0,40}") {
+        prop_assert_eq!(round_trip(&message, Packing::Huffman), Some(message.clone()));
+    }
+
+    #[test]
+    fn varints_round_trip(numbers in proptest::collection::vec(any::<u64>(), 1..6)) {
+        let message = numbers.iter().map(u64::to_string).collect::<Vec<_>>().join(",");
+        prop_assert_eq!(round_trip(&message, Packing::Varint), Some(message.clone()));
+    }
+}
+
+#[test]
+fn packing_shrinks_typical_messages() {
+    let cases = [
+        ("Take 7 of 12", Packing::SixBit),
+        ("meet at noon, ok?", Packing::FiveBit),
+        ("the rain in spain", Packing::Huffman),
+        ("20260418,7,1500", Packing::Varint),
+    ];
+    for (message, packing) in cases {
+        let packed = pack(message.as_bytes(), packing).unwrap();
+        assert!(packed.len() < message.len(), "{message:?} as {packing:?}: {} bytes", packed.len());
+        assert!(pack(message.as_bytes(), Packing::Smallest).unwrap().len() <= packed.len());
+    }
+    assert_eq!(pack(b"anything", Packing::None), None);
+    assert_eq!(pack("émigré ¿".as_bytes(), Packing::Smallest), None, "nothing shorter than raw UTF-8");
+}
+
+#[test]
+fn packed_messages_fit_where_raw_bytes_do_not() {
+    // 8 kHz QIM: 81 bits per frame leave 5 payload bytes after the pilot and header, and 7 raw
+    // bytes in the legacy layout an unpacked message falls back to
+    let mut engine = Engine::new();
+    let audio = generate(Signal::SpeechLike, 8000, 4.0);
+    let decode = DecodeOptions {
+        mode: Some(EmbeddingMode::Qim),
+        ..DecodeOptions::default()
+    };
+    for message in ["tea at ten", "20260418", "3,14,159"] {
+        let raw = EncodeOptions {
+            mode: EmbeddingMode::Qim,
+            ..EncodeOptions::default()
+        };
+        let (encoded, _) = engine.encode_with_options(&audio, 8000, message, &raw).unwrap();
+        let (decoded, _) = engine.decode_with_options(&encoded, 8000, &decode);
+        assert_ne!(decoded.message, message);
+
+        let packed = EncodeOptions { packing: Packing::Smallest, ..raw };
+        let (encoded, _) = engine.encode_with_options(&audio, 8000, message, &packed).unwrap();
+        let (decoded, _) = engine.decode_with_options(&encoded, 8000, &decode);
+        assert_eq!(decoded.message, message);
+        assert!(decoded.header.is_some_and(|header| header.is_packed()));
+    }
+}
+
+#[test]
+fn messages_outside_the_mode_go_out_unpacked() {
+    assert_eq!(pack(b"Take 7!", Packing::SixBit), None);
+    assert_eq!(pack(b"ALL CAPS", Packing::FiveBit), None);
+    assert_eq!(pack(b"7,x", Packing::Varint), None);
+
+    let mut engine = Engine::new();
+    let audio = generate(Signal::SpeechLike, 16_000, 4.0);
+    let encode = EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        packing: Packing::FiveBit,
+        ..EncodeOptions::default()
+    };
+    let decode = DecodeOptions {
+        mode: Some(EmbeddingMode::Qim),
+        ..DecodeOptions::default()
+    };
+    let (encoded, _) = engine.encode_with_options(&audio, 16_000, "Take 7!", &encode).unwrap();
+    let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &decode);
+    assert_eq!(decoded.message, "Take 7!");
+    assert!(decoded.header.is_some_and(|header| !header.is_packed()));
+}
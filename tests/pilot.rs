@@ -0,0 +1,176 @@
+//! Pilots: sync sequences, the correlation null distribution, and decoding with longer pilots.
+
+mod common;
+
+use common::{generate, paired, Rng, Signal};
+use msg_encoder::decoder::{bit_llrs_with_pilot, decode_audio_samples_with_options};
+use msg_encoder::encoder::encode_audio_samples_with_options;
+use msg_encoder::integrity::integrity_map;
+use msg_encoder::pilot::{correlation, detection_threshold, false_alarm_probability};
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions, FrameStatus, PayloadLayout, Pilot, PILOT_PATTERN};
+
+fn bipolar(bits: &[u8]) -> Vec<i32> {
+    bits.iter().map(|&bit| if bit == 1 { 1 } else { -1 }).collect()
+}
+
+#[test]
+fn sequences_have_low_off_peak_correlation() {
+    // Barker code: aperiodic sidelobes of at most 1
+    let barker = bipolar(&Pilot::Barker13.bits().unwrap());
+    for shift in 1..barker.len() {
+        let sidelobe: i32 = barker.iter().zip(&barker[shift..]).map(|(a, b)| a * b).sum();
+        assert!(sidelobe.abs() <= 1, "Barker-13 sidelobe {sidelobe} at shift {shift}");
+    }
+
+    // m-sequences: balanced, periodic autocorrelation -1 off peak
+    for degree in 3..=10 {
+        let pilot = Pilot::MSequence { degree };
+        let bits = pilot.bits().unwrap();
+        assert_eq!(bits.len(), pilot.length());
+        assert_eq!(bits.iter().filter(|&&bit| bit == 1).count(), 1 << (degree - 1), "degree {degree}");
+        let sequence = bipolar(&bits);
+        for shift in 1..sequence.len() {
+            let periodic: i32 = (0..sequence.len()).map(|i| sequence[i] * sequence[(i + shift) % sequence.len()]).sum();
+            assert_eq!(periodic, -1, "degree {degree}, shift {shift}");
+        }
+    }
+}
+
+/// Standard normal draw (sum of twelve uniforms)
+fn gaussian(rng: &mut Rng) -> f32 {
+    (0..12).map(|_| (rng.next_f32() + 1.0) / 2.0).sum::<f32>() - 6.0
+}
+
+#[test]
+fn threshold_holds_the_false_alarm_rate_on_noise() {
+    let mut rng = Rng::new(49);
+    for pilot in [Pilot::Alternating, Pilot::Barker13, Pilot::MSequence { degree: 5 }] {
+        let bits = pilot.bits().unwrap();
+        for target in [0.01, 0.1] {
+            let threshold = detection_threshold(bits.len(), target).unwrap();
+            assert!((false_alarm_probability(bits.len(), threshold).unwrap() - target).abs() < 1e-4);
+
+            let trials = 20_000;
+            let alarms = (0..trials)
+                .filter(|_| {
+                    let scores: Vec<f32> = (0..bits.len()).map(|_| gaussian(&mut rng) * 3.0 + 1.0).collect();
+                    correlation(&scores, &bits).abs() >= threshold
+                })
+                .count();
+            let rate = alarms as f64 / trials as f64;
+            assert!((rate - target).abs() < target * 0.25 + 0.002, "{pilot:?} at {target}: measured {rate}");
+        }
+    }
+
+    // Longer pilots need less correlation for the same rate
+    let short = detection_threshold(8, 0.01).unwrap();
+    let long = detection_threshold(63, 0.01).unwrap();
+    assert!(long < short / 2.0, "{short} vs {long}");
+}
+
+#[test]
+fn invalid_pilot_settings_are_errors() {
+    for degree in [0, 2, 11, 64] {
+        assert!(Pilot::MSequence { degree }.bits().is_err(), "degree {degree}");
+    }
+    assert!(false_alarm_probability(3, 0.5).is_err(), "too short for a null distribution");
+
+    // Raw pilots too short to judge read as no evidence rather than panicking
+    let scores = [0.2, 0.9, 0.1, 0.8];
+    assert_eq!(correlation(&scores[..1], &PILOT_PATTERN), 0.0, "fewer scores than pilot bits");
+    for pilot in [&[][..], &[1][..], &[0, 1][..]] {
+        assert_eq!(correlation(&scores, pilot), if pilot.len() < 2 { 0.0 } else { 1.0 }, "{pilot:?}");
+        let llrs = bit_llrs_with_pilot(&scores, pilot, false);
+        assert!(llrs.iter().all(|llr| llr.is_finite()), "{pilot:?}: {llrs:?}");
+    }
+    for false_alarm in [0.0, -0.1, 1.5, f64::NAN] {
+        assert!(detection_threshold(8, false_alarm).is_err(), "false alarm {false_alarm}");
+    }
+
+    // Encoding refuses them; decoding finds nothing rather than panicking
+    let audio = generate(Signal::SpeechLike, 16_000, 1.0);
+    let encode = EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        pilot: Pilot::MSequence { degree: 12 },
+        ..EncodeOptions::default()
+    };
+    assert!(encode_audio_samples_with_options(&audio, 16_000, "hi", &encode).is_err());
+    let (encode, decode) = paired(EncodeOptions { mode: EmbeddingMode::Qim, ..EncodeOptions::default() });
+    let (encoded, _) = encode_audio_samples_with_options(&audio, 16_000, "hi", &encode).unwrap();
+    for decode in [
+        DecodeOptions { pilot: Pilot::MSequence { degree: 12 }, ..decode.clone() },
+        DecodeOptions { false_alarm: Some(2.0), ..decode.clone() },
+    ] {
+        let (decoded, _) = decode_audio_samples_with_options(&encoded, 16_000, &decode);
+        assert_eq!(decoded.message, "");
+        assert!(integrity_map(&encoded, 16_000, &decode).is_err());
+    }
+}
+
+#[test]
+fn longer_pilots_round_trip_and_must_match() {
+    let audio = generate(Signal::SpeechLike, 16_000, 4.0);
+    for pilot in [Pilot::Barker13, Pilot::MSequence { degree: 5 }] {
+        for (layout, message) in [(PayloadLayout::Repeated, "pilot"), (PayloadLayout::Segmented, "a segmented message")] {
+            let (encode, decode) = paired(EncodeOptions {
+                mode: EmbeddingMode::Qim,
+                layout,
+                pilot,
+                ..EncodeOptions::default()
+            });
+            let (encoded, _) = encode_audio_samples_with_options(&audio, 16_000, message, &encode).unwrap();
+            let (decoded, viz) = decode_audio_samples_with_options(&encoded, 16_000, &decode);
+            assert_eq!(decoded.message, message, "{pilot:?} {layout:?}");
+            assert_eq!(viz.bit_sequence[..pilot.length()], pilot.bits().unwrap());
+
+            // Every marked frame is found; against the default pilot, none reads as intact
+            let report = integrity_map(&encoded, 16_000, &decode).unwrap();
+            assert!(report.frames.iter().all(|frame| frame.status != FrameStatus::Missing), "{pilot:?} {layout:?}");
+            let wrong = DecodeOptions { pilot: Pilot::Alternating, ..decode };
+            let report = integrity_map(&encoded, 16_000, &wrong).unwrap();
+            assert!(report.frames.iter().all(|frame| frame.status != FrameStatus::Intact), "{pilot:?} {layout:?}");
+        }
+    }
+}
+
+#[test]
+fn unmarked_audio_rarely_passes_a_long_pilot() {
+    let decode = DecodeOptions {
+        mode: Some(EmbeddingMode::Qim),
+        pilot: Pilot::MSequence { degree: 6 },
+        false_alarm: Some(0.01),
+        ..DecodeOptions::default()
+    };
+    for signal in [Signal::WhiteNoise, Signal::PinkNoise, Signal::SpeechLike] {
+        let report = integrity_map(&generate(signal, 16_000, 6.0), 16_000, &decode).unwrap();
+        let found = report.frames.iter().filter(|frame| frame.status != FrameStatus::Missing).count();
+        assert!(found * 20 <= report.frames.len(), "{signal:?}: {found} of {} frames", report.frames.len());
+    }
+}
+
+#[test]
+fn unmarked_recordings_are_not_decoded_by_default() {
+    for mode in [EmbeddingMode::Multiplicative, EmbeddingMode::Qim, EmbeddingMode::Phase] {
+        let decode = DecodeOptions {
+            mode: Some(mode),
+            ..DecodeOptions::default()
+        };
+        for signal in [Signal::WhiteNoise, Signal::PinkNoise, Signal::SineSweep, Signal::SpeechLike] {
+            let audio = generate(signal, 16_000, 4.0);
+            let (decoded, _) = decode_audio_samples_with_options(&audio, 16_000, &decode);
+            assert_eq!(decoded.raw_bytes, Vec::<u8>::new(), "{mode:?} {signal:?}: read a mark from unmarked audio");
+        }
+    }
+}
+
+#[test]
+fn a_valid_header_vouches_for_weak_pilots() {
+    // Broadband noise at 20 ms: few frames clear the strict pilot threshold, but the voted header reads clean
+    let audio = generate(Signal::WhiteNoise, 16_000, 3.0);
+    let encode = EncodeOptions { frame_duration_ms: 20, ..EncodeOptions::default() };
+    for message in ["hi", "hello", "helloword"] {
+        let (encoded, _) = encode_audio_samples_with_options(&audio, 16_000, message, &encode).unwrap();
+        let (decoded, _) = decode_audio_samples_with_options(&encoded, 16_000, &DecodeOptions::default());
+        assert_eq!(decoded.message, message);
+    }
+}
@@ -0,0 +1,82 @@
+//! The QIM error bound: any per-bin log-magnitude perturbation smaller than a
+//! quarter step leaves every bit readable, at every strength.
+
+mod common;
+
+use common::{generate, Signal};
+use msg_encoder::decoder::decode_audio_samples_with_options;
+use msg_encoder::encoder::encode_audio_samples_with_options;
+use msg_encoder::qim::{embed_qim, qim_scores, qim_step_db, DITHER_FRAMES, QIM_STEP_DB};
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions};
+use proptest::prelude::*;
+use realfft::num_complex::Complex;
+
+const SPECTRUM_LEN: usize = 129; // 256-point FFT
+const START_BIN: usize = 48;
+
+proptest! {
+    #[test]
+    fn qim_survives_sub_quarter_step_perturbation(
+        bins in proptest::collection::vec((0.0f32..50.0, -3.0f32..3.0), SPECTRUM_LEN),
+        bits in proptest::collection::vec(0u8..=1, SPECTRUM_LEN - START_BIN),
+        noise in proptest::collection::vec(-0.99f32..0.99, SPECTRUM_LEN),
+        frame_index in 0usize..1000,
+        strength_percent in 1u32..100,
+    ) {
+        let step_db = qim_step_db(strength_percent);
+        let mut spectrum: Vec<Complex<f32>> =
+            bins.iter().map(|&(mag, phase)| Complex::from_polar(mag, phase)).collect();
+        spectrum[SPECTRUM_LEN - 1].im = 0.0;
+        embed_qim(&mut spectrum, &bits, START_BIN, frame_index, step_db);
+
+        // Scale each magnitude by up to ±(step/4) dB (strictly inside the bound)
+        let magnitudes: Vec<f32> = spectrum[START_BIN..]
+            .iter()
+            .zip(&noise[START_BIN..])
+            .map(|(bin, &n)| bin.norm() * 10f32.powf(n * step_db / 4.0 / 20.0))
+            .collect();
+
+        let mut scores = Vec::new();
+        qim_scores(&magnitudes, START_BIN, SPECTRUM_LEN, frame_index, step_db, &mut scores);
+        let decoded: Vec<u8> = scores.iter().map(|&s| u8::from(s > 0.0)).collect();
+        prop_assert_eq!(decoded, bits);
+    }
+}
+
+#[test]
+fn step_follows_strength_and_dither_follows_the_frame() {
+    assert_eq!(qim_step_db(15), QIM_STEP_DB);
+    assert!(qim_step_db(5) < QIM_STEP_DB && qim_step_db(30) > QIM_STEP_DB);
+
+    let flat = vec![Complex::new(10.0f32, 0.0); SPECTRUM_LEN];
+    let bits = vec![0u8; SPECTRUM_LEN - START_BIN];
+    let marked = |frame_index: usize| {
+        let mut spectrum = flat.clone();
+        embed_qim(&mut spectrum, &bits, START_BIN, frame_index, QIM_STEP_DB);
+        spectrum
+    };
+    for frame_index in 1..DITHER_FRAMES {
+        assert_ne!(marked(0), marked(frame_index), "frame {frame_index}");
+    }
+    assert_eq!(marked(0), marked(DITHER_FRAMES), "the dither repeats every DITHER_FRAMES");
+}
+
+#[test]
+fn decoder_needs_the_encoders_strength() {
+    let audio = generate(Signal::SpeechLike, 16_000, 3.0);
+    let encode = EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        strength_percent: 40,
+        ..EncodeOptions::default()
+    };
+    let (encoded, _) = encode_audio_samples_with_options(&audio, 16_000, "louder", &encode).unwrap();
+    let decode = DecodeOptions {
+        mode: Some(EmbeddingMode::Qim),
+        strength_percent: Some(40),
+        ..DecodeOptions::default()
+    };
+    assert_eq!(decode_audio_samples_with_options(&encoded, 16_000, &decode).0.message, "louder");
+
+    let wrong = DecodeOptions { strength_percent: None, ..decode };
+    assert_ne!(decode_audio_samples_with_options(&encoded, 16_000, &wrong).0.message, "louder");
+}
@@ -0,0 +1,153 @@
+//! The `WatermarkScheme` extension point: built-in lookup and a scheme defined outside the crate.
+
+mod common;
+
+use common::{generate, Signal};
+use msg_encoder::encoder::{build_legacy_bit_sequence, check_message, START_BIN};
+use msg_encoder::phase;
+use msg_encoder::scheme::{AnalysisFrame, EmbedFrame};
+use msg_encoder::packing::Packing;
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions, Engine, Fec, PayloadLayout, WatermarkScheme};
+
+/// Phase coding with the bit polarity reversed (1 → -π/2), built from the crate's phase helpers.
+struct ReversedPhase;
+
+impl WatermarkScheme for ReversedPhase {
+    fn id(&self) -> &'static str {
+        "reversed-phase"
+    }
+
+    fn capacity(&self, spectrum_len: usize) -> usize {
+        spectrum_len.saturating_sub(START_BIN + 1)
+    }
+
+    fn embed_frame(&self, frame: &mut EmbedFrame<'_>) {
+        phase::embed_phase(frame.spectrum, frame.bits, START_BIN);
+        for bin in &mut frame.spectrum[START_BIN..] {
+            *bin = bin.conj();
+        }
+    }
+
+    fn analyse_frame(&self, frame: &mut AnalysisFrame<'_>, scores: &mut Vec<f32>) {
+        phase::phase_scores(&frame.spectrum[START_BIN..], scores);
+        for score in scores.iter_mut() {
+            *score = -*score;
+        }
+    }
+}
+
+#[test]
+fn built_in_schemes_are_found_by_id() {
+    for mode in EmbeddingMode::ALL {
+        let id = mode.scheme().id();
+        assert_eq!(id.parse::<EmbeddingMode>(), Ok(mode));
+        assert_eq!(id.to_uppercase().parse::<EmbeddingMode>(), Ok(mode));
+    }
+    assert!("reversed-phase".parse::<EmbeddingMode>().is_err());
+}
+
+#[test]
+fn custom_scheme_round_trips() {
+    let mut engine = Engine::new();
+    for rate in [8000, 16_000] {
+        let audio = generate(Signal::SpeechLike, rate, 3.0);
+        let (encoded, _) =
+            engine.encode_with_scheme(&ReversedPhase, &audio, rate, "hello", &EncodeOptions::default()).unwrap();
+        assert_ne!(encoded, audio);
+        let (decoded, _) = engine.decode_with_scheme(&ReversedPhase, &encoded, rate, &DecodeOptions::default());
+        assert_eq!(decoded.message, "hello", "{rate} Hz");
+    }
+}
+
+#[test]
+fn built_in_scheme_matches_its_mode() {
+    let audio = generate(Signal::PinkNoise, 16_000, 3.0);
+    let options = EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        ..EncodeOptions::default()
+    };
+    let mut engine = Engine::new();
+    let (by_mode, _) = engine.encode_with_options(&audio, 16_000, "hi", &options).unwrap();
+    let (by_scheme, _) =
+        engine.encode_with_scheme(EmbeddingMode::Qim.scheme(), &audio, 16_000, "hi", &EncodeOptions::default()).unwrap();
+    assert_eq!(by_mode, by_scheme);
+}
+
+#[test]
+fn decoding_reads_the_scheme_frame_and_layout_from_the_header() {
+    let audio = generate(Signal::SpeechLike, 16_000, 4.0);
+    let mut engine = Engine::new();
+    for (mode, frame_duration_ms, layout, message) in [
+        (EmbeddingMode::Qim, 32, PayloadLayout::Repeated, "qim"),
+        (EmbeddingMode::Phase, 64, PayloadLayout::Repeated, "phase, long frames"),
+        (EmbeddingMode::Multiplicative, 64, PayloadLayout::Repeated, "multiplicative"),
+        (EmbeddingMode::Qim, 32, PayloadLayout::Segmented, "a message long enough to be split over several segments"),
+    ] {
+        let options = EncodeOptions {
+            mode,
+            frame_duration_ms,
+            layout,
+            ..EncodeOptions::default()
+        };
+        let (encoded, _) = engine.encode_with_options(&audio, 16_000, message, &options).unwrap();
+        let configured = msg_encoder::decoder::configure(&mut engine, &encoded, 16_000, &DecodeOptions::default());
+        assert_eq!(
+            (configured.mode, configured.frame_duration_ms, configured.layout),
+            (Some(mode), Some(frame_duration_ms), Some(layout))
+        );
+        let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &DecodeOptions::default());
+        assert_eq!(decoded.message, message, "{mode:?} {frame_duration_ms} ms {layout:?}");
+    }
+}
+
+#[test]
+fn explicit_decode_options_override_the_header() {
+    let audio = generate(Signal::SpeechLike, 16_000, 3.0);
+    let options = EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        frame_duration_ms: 64,
+        ..EncodeOptions::default()
+    };
+    let mut engine = Engine::new();
+    let (encoded, _) = engine.encode_with_options(&audio, 16_000, "hello", &options).unwrap();
+
+    let matching = DecodeOptions {
+        frame_duration_ms: Some(64),
+        ..DecodeOptions::default()
+    };
+    let configured = msg_encoder::decoder::configure(&mut engine, &encoded, 16_000, &matching);
+    assert_eq!((configured.mode, configured.layout), (Some(EmbeddingMode::Qim), Some(PayloadLayout::Repeated)));
+
+    // A frame duration the recording was not written with is used as given, and reads nothing
+    let mismatched = DecodeOptions {
+        frame_duration_ms: Some(20),
+        ..DecodeOptions::default()
+    };
+    let configured = msg_encoder::decoder::configure(&mut engine, &encoded, 16_000, &mismatched);
+    assert_eq!(configured.frame_duration_ms, Some(20));
+    let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &mismatched);
+    assert_ne!(decoded.message, "hello");
+}
+
+#[test]
+fn coding_that_does_not_fit_is_an_error() {
+    // 8 kHz / 32 ms leaves 81 bits: 7 plain bytes fit only in the legacy layout, which has no packing or FEC
+    let audio = generate(Signal::SpeechLike, 8000, 3.0);
+    let mut engine = Engine::new();
+    let plain = EncodeOptions { mode: EmbeddingMode::Qim, ..EncodeOptions::default() };
+    let (_, viz) = engine.encode_with_options(&audio, 8000, "hello!!", &plain).unwrap();
+    assert_eq!(viz.bit_sequence, build_legacy_bit_sequence("hello!!"));
+
+    for coded in [
+        EncodeOptions { fec: Fec::Half, ..plain.clone() },
+        EncodeOptions { packing: Packing::SixBit, ..plain.clone() },
+    ] {
+        assert!(engine.encode_with_options(&audio, 8000, "hello!!", &coded).is_err(), "{:?} {:?}", coded.fec, coded.packing);
+    }
+
+    // Too long for a header: plain messages still go out in the legacy layout, coded ones are refused up front
+    let long = "x".repeat(400); // 300 bytes even at 6 bits per character
+    assert_eq!(check_message(&long, &plain), Ok(()));
+    let packed = EncodeOptions { packing: Packing::SixBit, ..plain };
+    assert!(check_message(&long, &packed).is_err());
+}
@@ -0,0 +1,205 @@
+//! The segmented layout: messages longer than one frame, split over frames and voted across repeats,
+//! and decoding excerpts cropped out of a longer recording.
+
+mod common;
+
+use common::{generate, paired, Signal};
+use msg_encoder::encoder::{build_legacy_bit_sequence, check_message, SAMPLE_RATES};
+use msg_encoder::segment::{split_stream, SegmentField, SEGMENT_FIELD_BITS};
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions, Engine, PayloadLayout, PILOT_PATTERN};
+
+const SECONDS: f32 = 6.0;
+
+/// 93 bytes: more than ten times what one 16 kHz frame holds in the repeated layout.
+const LONG_MESSAGE: &str =
+    "Segmented payloads trade time for space: each frame carries one slice, and the cycle repeats.";
+
+/// Where the excerpts start: well into the file, mid-frame and mid-cycle.
+const CROP_START_SECONDS: f32 = 2.37;
+
+#[test]
+fn segment_field_survives_one_flipped_bit() {
+    for count in 1..=255 {
+        for index in [0, count / 2, count - 1] {
+            let field = SegmentField { index, count };
+            let mut bits = field.to_bits();
+            assert_eq!(bits.len(), SEGMENT_FIELD_BITS);
+            assert_eq!(SegmentField::from_bits(&bits), Some(field));
+            bits[(index + count) % SEGMENT_FIELD_BITS] ^= 1;
+            assert_eq!(SegmentField::from_bits(&bits), Some(field), "{field:?}");
+        }
+    }
+}
+
+#[test]
+fn split_stream_covers_the_stream_once() {
+    let stream: Vec<u8> = (0..1000).map(|i| (i * 7 % 3 % 2) as u8).collect();
+    let segments = split_stream(&stream, 49);
+    assert_eq!(segments.len(), 21);
+
+    let header_len = PILOT_PATTERN.len() + SEGMENT_FIELD_BITS;
+    let mut rebuilt = Vec::new();
+    for (index, segment) in segments.iter().enumerate() {
+        assert_eq!(&segment[..PILOT_PATTERN.len()], &PILOT_PATTERN[..]);
+        let field = SegmentField::from_bits(&segment[PILOT_PATTERN.len()..]).unwrap();
+        assert_eq!(field, SegmentField { index, count: 21 });
+        rebuilt.extend_from_slice(&segment[header_len..]);
+    }
+    assert_eq!(rebuilt, stream);
+}
+
+#[test]
+fn long_message_round_trips_segmented() {
+    let mut engine = Engine::new();
+    for mode in [EmbeddingMode::Qim, EmbeddingMode::Phase] {
+        let (encode, decode) = paired(EncodeOptions {
+            mode,
+            layout: PayloadLayout::Segmented,
+            ..EncodeOptions::default()
+        });
+        for (&rate, signal) in SAMPLE_RATES.iter().zip([Signal::SpeechLike, Signal::PinkNoise, Signal::SineSweep]) {
+            let audio = generate(signal, rate, SECONDS);
+            let (encoded, _) = engine.encode_with_options(&audio, rate, LONG_MESSAGE, &encode).unwrap();
+            let (decoded, _) = engine.decode_with_options(&encoded, rate, &decode);
+            assert_eq!(decoded.message, LONG_MESSAGE, "{mode:?} {signal:?} {rate} Hz");
+            assert!(decoded.header.is_some_and(|header| header.is_segmented()));
+        }
+    }
+}
+
+#[test]
+fn repeated_layout_truncates_what_segmenting_recovers() {
+    let mut engine = Engine::new();
+    let audio = generate(Signal::PinkNoise, 16_000, SECONDS);
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        layout: PayloadLayout::Segmented,
+        ..EncodeOptions::default()
+    });
+
+    let repeated_encode = EncodeOptions { layout: PayloadLayout::Repeated, ..encode.clone() };
+    let repeated_decode = DecodeOptions { layout: Some(PayloadLayout::Repeated), ..decode.clone() };
+    let (repeated, _) = engine.encode_with_options(&audio, 16_000, LONG_MESSAGE, &repeated_encode).unwrap();
+    let (decoded, _) = engine.decode_with_options(&repeated, 16_000, &repeated_decode);
+    assert_ne!(decoded.message, LONG_MESSAGE);
+
+    let (segmented, _) = engine.encode_with_options(&audio, 16_000, LONG_MESSAGE, &encode).unwrap();
+    let (decoded, _) = engine.decode_with_options(&segmented, 16_000, &decode);
+    assert_eq!(decoded.message, LONG_MESSAGE);
+}
+
+/// `seconds` of `samples` from `CROP_START_SECONDS`.
+fn crop(samples: &[f32], sample_rate: u32, seconds: f32) -> &[f32] {
+    let start = (CROP_START_SECONDS * sample_rate as f32) as usize;
+    &samples[start..start + (seconds * sample_rate as f32) as usize]
+}
+
+#[test]
+fn cropped_excerpt_decodes_with_alignment_search() {
+    let mut engine = Engine::new();
+    for mode in [EmbeddingMode::Qim, EmbeddingMode::Phase] {
+        let (encode, decode) = paired(EncodeOptions {
+            mode,
+            layout: PayloadLayout::Segmented,
+            ..EncodeOptions::default()
+        });
+        let decode = DecodeOptions { search_alignment: true, ..decode };
+        for rate in [8000, 16_000] {
+            let audio = generate(Signal::SpeechLike, rate, SECONDS);
+            let (encoded, viz) = engine.encode_with_options(&audio, rate, LONG_MESSAGE, &encode).unwrap();
+            let count = SegmentField::from_bits(&viz.bit_sequence[PILOT_PATTERN.len()..]).unwrap().count;
+
+            let (decoded, _) = engine.decode_with_options(crop(&encoded, rate, 2.0), rate, &decode);
+            assert_eq!(decoded.message, LONG_MESSAGE, "{mode:?} {rate} Hz");
+            assert_eq!(decoded.segments_seen, (0..count).collect::<Vec<_>>(), "{mode:?} {rate} Hz");
+        }
+    }
+}
+
+#[test]
+fn cropped_excerpt_decodes_repeated_layout() {
+    let mut engine = Engine::new();
+    let audio = generate(Signal::PinkNoise, 16_000, SECONDS);
+    let encoded = engine.encode_audio_samples(&audio, 16_000, "hello", 32, 15);
+    let excerpt = crop(&encoded, 16_000, 1.0);
+
+    let decode = DecodeOptions {
+        search_alignment: true,
+        ..DecodeOptions::default()
+    };
+    let (decoded, _) = engine.decode_with_options(excerpt, 16_000, &decode);
+    assert_eq!(decoded.message, "hello");
+    assert!(decoded.segments_seen.is_empty());
+}
+
+#[test]
+fn excerpt_shorter_than_the_cycle_reports_the_segments_it_holds() {
+    let mut engine = Engine::new();
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        layout: PayloadLayout::Segmented,
+        ..EncodeOptions::default()
+    });
+    let decode = DecodeOptions { search_alignment: true, ..decode };
+    let audio = generate(Signal::PinkNoise, 8000, SECONDS);
+    let (encoded, viz) = engine.encode_with_options(&audio, 8000, LONG_MESSAGE, &encode).unwrap();
+    let count = SegmentField::from_bits(&viz.bit_sequence[PILOT_PATTERN.len()..]).unwrap().count;
+
+    // 16 segments of 32 ms: a quarter of a second holds about half of them
+    let excerpt = crop(&encoded, 8000, 0.25);
+    let (decoded, _) = engine.decode_with_options(excerpt, 8000, &decode);
+    assert_eq!(decoded.message, "");
+    assert!(decoded.header.is_none());
+    assert!(!decoded.segments_seen.is_empty() && decoded.segments_seen.len() < count, "{:?}", decoded.segments_seen);
+    assert!(decoded.segments_seen.iter().all(|&index| index < count));
+}
+
+#[test]
+fn messages_too_long_for_a_header_fall_back_instead_of_panicking() {
+    let mut engine = Engine::new();
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        layout: PayloadLayout::Segmented,
+        ..EncodeOptions::default()
+    });
+    let audio = generate(Signal::PinkNoise, 16_000, SECONDS);
+
+    // 300 bytes: more than a header counts, so the message goes out whole in the legacy layout
+    let long = LONG_MESSAGE.repeat(4);
+    assert_eq!(check_message(&long, &encode), Ok(()));
+    let (encoded, viz) = engine.encode_with_options(&audio, 16_000, &long, &encode).unwrap();
+    assert_eq!(viz.bit_sequence, build_legacy_bit_sequence(&long));
+    let (decoded, _) = engine.decode_with_options(&encoded, 16_000, &decode);
+    assert!(decoded.header.is_none());
+
+    // A sealed payload cannot ride in a legacy stream: the caller hears about it before encoding
+    let sealed = EncodeOptions { encryption_key: Some(b"secret".to_vec()), ..encode };
+    assert!(check_message(&long[..240], &sealed).is_err(), "240 bytes and the seal overflow the header");
+    assert_eq!(check_message(&long[..200], &sealed), Ok(()));
+    let refused = engine.encode_with_options(&audio, 16_000, &long[..240], &sealed);
+    assert_eq!(refused.map(|_| ()), check_message(&long[..240], &sealed), "encoding refuses it the same way");
+}
+
+#[test]
+fn segments_without_a_header_decode_to_nothing() {
+    let mut engine = Engine::new();
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        layout: PayloadLayout::Segmented,
+        ..EncodeOptions::default()
+    });
+    let audio = generate(Signal::PinkNoise, 8000, SECONDS);
+    let (mut encoded, viz) = engine.encode_with_options(&audio, 8000, LONG_MESSAGE, &encode).unwrap();
+    let count = SegmentField::from_bits(&viz.bit_sequence[PILOT_PATTERN.len()..]).unwrap().count;
+
+    // Noise over every frame holding segment 0: its field still reads, the header in it does not
+    let noise = generate(Signal::WhiteNoise, 8000, SECONDS);
+    let frame_len = 256; // 32 ms at 8 kHz
+    for (marked, noise) in encoded.chunks_mut(frame_len).zip(noise.chunks(frame_len)).step_by(count) {
+        marked.iter_mut().zip(noise).for_each(|(sample, noise)| *sample += 0.05 * noise);
+    }
+    let (decoded, _) = engine.decode_with_options(&encoded, 8000, &decode);
+    assert_eq!(decoded.message, "");
+    assert!(decoded.header.is_none());
+    assert_eq!(decoded.segments_seen.len(), count, "every segment was still placed");
+}
@@ -0,0 +1,110 @@
+//! Adaptive thresholds: the EM-fitted curve across the band, and decoding with it.
+
+mod common;
+
+use common::{generate, paired, Rng, Signal};
+use msg_encoder::decoder::{decode_audio_samples_with_options, pilot_stats};
+use msg_encoder::encoder::encode_audio_samples_with_options;
+use msg_encoder::threshold::{flatten_scores, threshold_curve};
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions, Interleaver, PayloadLayout, PILOT_PATTERN};
+
+/// Pilot + random bits scored as `bit + baseline(bin) + noise`, with their bits
+fn sloped_scores(rng: &mut Rng, len: usize, baseline: impl Fn(usize) -> f32) -> (Vec<f32>, Vec<u8>) {
+    let mut bits = PILOT_PATTERN.to_vec();
+    bits.extend((PILOT_PATTERN.len()..len).map(|_| (rng.next_u64() & 1) as u8));
+    let scores = bits
+        .iter()
+        .enumerate()
+        .map(|(bin, &bit)| f32::from(bit) + baseline(bin) + 0.1 * rng.next_f32())
+        .collect();
+    (scores, bits)
+}
+
+fn errors(scores: &[f32], threshold: impl Fn(usize) -> f32, bits: &[u8]) -> usize {
+    scores.iter().enumerate().zip(bits).filter(|&((i, &score), &bit)| u8::from(score >= threshold(i)) != bit).count()
+}
+
+#[test]
+fn curve_follows_a_sloped_baseline() {
+    let mut rng = Rng::new(50);
+    let len = 200;
+    let bins: Vec<usize> = (0..len).collect();
+    let (scores, bits) = sloped_scores(&mut rng, len, |bin| 2.5 * bin as f32 / len as f32);
+
+    // The pilot's threshold reads most of the upper band as ones
+    let (_, _, global) = pilot_stats(&scores);
+    assert!(errors(&scores, |_| global, &bits) > len / 4);
+
+    let curve = threshold_curve(&scores, &bins, &PILOT_PATTERN);
+    assert!(errors(&scores, |i| curve[i], &bits) <= 2, "{curve:?}");
+    assert!(curve[len - 1] - curve[0] > 1.5, "the curve should climb with the baseline");
+
+    // Flattened scores read right against the single threshold
+    let flattened = flatten_scores(&scores, &bins, &PILOT_PATTERN);
+    let (_, _, threshold) = pilot_stats(&flattened);
+    assert!(errors(&flattened, |_| threshold, &bits) <= 2);
+}
+
+#[test]
+fn curve_stays_flat_without_a_baseline_and_keeps_polarity() {
+    let mut rng = Rng::new(5);
+    let len = 160;
+    let bins: Vec<usize> = (0..len).collect();
+    let (scores, bits) = sloped_scores(&mut rng, len, |_| 0.0);
+    let (_, _, global) = pilot_stats(&scores);
+    let curve = threshold_curve(&scores, &bins, &PILOT_PATTERN);
+    assert!(curve.iter().all(|&t| (t - global).abs() < 0.15), "{global} vs {curve:?}");
+
+    // Inverted scores: ones low, still split in the right place
+    let inverted: Vec<f32> = scores.iter().map(|&s| -s + 0.02 * s * s).collect();
+    let curve = threshold_curve(&inverted, &bins, &PILOT_PATTERN);
+    let wrong = inverted.iter().zip(&curve).zip(&bits).filter(|&((&s, &t), &bit)| u8::from(s < t) != bit).count();
+    assert_eq!(wrong, 0);
+}
+
+#[test]
+fn decoding_with_the_curve_round_trips() {
+    let audio = generate(Signal::SpeechLike, 16_000, 4.0);
+    for (mode, layout, interleaver, message) in [
+        (EmbeddingMode::Qim, PayloadLayout::Repeated, Interleaver::None, "adaptive"),
+        (EmbeddingMode::Phase, PayloadLayout::Repeated, Interleaver::None, "curve"),
+        (EmbeddingMode::Qim, PayloadLayout::Repeated, Interleaver::Random { seed: 50 }, "shuffled bins"),
+        (EmbeddingMode::Qim, PayloadLayout::Segmented, Interleaver::Block { rows: 8 }, "a message split over segments"),
+    ] {
+        let encode = EncodeOptions {
+            mode,
+            layout,
+            interleaver,
+            ..EncodeOptions::default()
+        };
+        let decode = DecodeOptions {
+            mode: Some(mode),
+            layout: Some(layout),
+            interleaver,
+            adaptive_threshold: true,
+            ..DecodeOptions::default()
+        };
+        let (encoded, _) = encode_audio_samples_with_options(&audio, 16_000, message, &encode).unwrap();
+        let (decoded, _) = decode_audio_samples_with_options(&encoded, 16_000, &decode);
+        assert_eq!(decoded.message, message, "{mode:?} {layout:?} {interleaver:?}");
+    }
+}
+
+#[test]
+fn curve_recovers_multiplicative_marks_on_speech() {
+    // Speech's sloping spectrum pulls the spectral-contrast scores away from the pilot's midpoint
+    let audio = generate(Signal::SpeechLike, 32_000, 4.0);
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Multiplicative,
+        layout: PayloadLayout::Segmented,
+        ..EncodeOptions::default()
+    });
+    let (encoded, _) = encode_audio_samples_with_options(&audio, 32_000, "helloword", &encode).unwrap();
+
+    let (pilot_only, _) = decode_audio_samples_with_options(&encoded, 32_000, &decode);
+    assert_ne!(pilot_only.message, "helloword", "the pilot threshold alone should misread this");
+
+    let adaptive = DecodeOptions { adaptive_threshold: true, ..decode };
+    let (decoded, _) = decode_audio_samples_with_options(&encoded, 32_000, &adaptive);
+    assert_eq!(decoded.message, "helloword");
+}
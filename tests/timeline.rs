@@ -0,0 +1,134 @@
+//! Timelines: payloads that change block by block, recovered with the time range each one covers.
+
+mod common;
+
+use common::{generate, paired, Signal};
+use msg_encoder::timeline::{decode_timeline, encode_timeline};
+use msg_encoder::{DecodeOptions, EmbeddingMode, EncodeOptions, Engine, Fec, Packing, TimelineEntry};
+
+/// Decoder frames are 32 ms; entry boundaries may land up to a frame away from the requested start.
+const FRAME_SECONDS: f32 = 0.032;
+
+fn assert_timeline(timeline: &[TimelineEntry], expected: &[(f32, f32, &str)]) {
+    let messages: Vec<&str> = timeline.iter().map(|entry| entry.message.as_str()).collect();
+    let expected_messages: Vec<&str> = expected.iter().map(|&(_, _, message)| message).collect();
+    assert_eq!(messages, expected_messages);
+    for (entry, &(start, end, _)) in timeline.iter().zip(expected) {
+        assert!((entry.start_seconds - start).abs() <= FRAME_SECONDS, "{entry:?} should start at {start}");
+        assert!((entry.end_seconds - end).abs() <= FRAME_SECONDS, "{entry:?} should end at {end}");
+    }
+}
+
+#[test]
+fn listed_entries_decode_with_their_time_ranges() {
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        ..EncodeOptions::default()
+    });
+    let audio = generate(Signal::SpeechLike, 16_000, 6.0);
+    let payloads = [(0.0, "t=0".to_string()), (2.0, "t=2".to_string()), (4.0, "t=4".to_string())];
+
+    let (encoded, _) = encode_timeline(&audio, 16_000, &payloads, &encode).unwrap();
+    let timeline = decode_timeline(&encoded, 16_000, &decode);
+    assert_timeline(&timeline, &[(0.0, 2.0, "t=0"), (2.0, 4.0, "t=2"), (4.0, 6.0, "t=4")]);
+}
+
+#[test]
+fn generated_block_payloads_decode_in_order() {
+    let mut engine = Engine::new();
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Phase,
+        ..EncodeOptions::default()
+    });
+    let audio = generate(Signal::PinkNoise, 8000, 5.0);
+
+    let mut counter = 0;
+    let (encoded, _) = engine.encode_timeline_with(
+        &audio,
+        8000,
+        1.0,
+        |_| {
+            counter += 1;
+            format!("#{}", counter - 1)
+        },
+        &encode,
+    )
+    .unwrap();
+    assert_eq!(counter, 5);
+
+    let timeline = engine.decode_timeline(&encoded, 8000, &decode);
+    let expected: Vec<(f32, f32, String)> =
+        (0..5).map(|block| (block as f32, block as f32 + 1.0, format!("#{block}"))).collect();
+    let expected: Vec<(f32, f32, &str)> = expected.iter().map(|(s, e, m)| (*s, *e, m.as_str())).collect();
+    assert_timeline(&timeline, &expected);
+}
+
+#[test]
+fn single_payload_spans_the_recording() {
+    let mut engine = Engine::new();
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        ..EncodeOptions::default()
+    });
+    let audio = generate(Signal::WhiteNoise, 8000, 3.0);
+
+    let (encoded, _) = engine.encode_timeline(&audio, 8000, &[(0.0, "fixed".to_string())], &encode).unwrap();
+    let timeline = engine.decode_timeline(&encoded, 8000, &decode);
+    assert_timeline(&timeline, &[(0.0, 3.0, "fixed")]);
+    assert_eq!(timeline[0].raw_bytes, b"fixed");
+}
+
+#[test]
+fn payload_coding_options_apply_to_every_entry() {
+    let (encode, decode) = paired(EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        ..EncodeOptions::default()
+    });
+    let payloads = [(0.0, "Take 1".to_string()), (2.0, "Take 2".to_string())];
+    let expected = [(0.0, 2.0, "Take 1"), (2.0, 4.0, "Take 2")];
+
+    // Packed, tagged and FEC-coded entries fit a 16 kHz frame
+    let audio = generate(Signal::SpeechLike, 16_000, 4.0);
+    let encode_coded = EncodeOptions {
+        packing: Packing::SixBit,
+        auth_key: Some(b"tag key".to_vec()),
+        fec: Fec::Half,
+        ..encode.clone()
+    };
+    let decode_coded = DecodeOptions { auth_key: Some(b"tag key".to_vec()), ..decode.clone() };
+    let (encoded, _) = encode_timeline(&audio, 16_000, &payloads, &encode_coded).unwrap();
+    let timeline = decode_timeline(&encoded, 16_000, &decode_coded);
+    assert_timeline(&timeline, &expected);
+    assert!(timeline.iter().all(|entry| entry.authenticated == Some(true)));
+
+    // Sealed entries need the larger frames of 32 kHz audio, and the secret to read
+    let audio = generate(Signal::SpeechLike, 32_000, 4.0);
+    let encode_sealed = EncodeOptions { encryption_key: Some(b"secret".to_vec()), ..encode };
+    let (encoded, _) = encode_timeline(&audio, 32_000, &payloads, &encode_sealed).unwrap();
+    let decode_sealed = DecodeOptions { encryption_key: Some(b"secret".to_vec()), ..decode.clone() };
+    let timeline = decode_timeline(&encoded, 32_000, &decode_sealed);
+    assert_timeline(&timeline, &expected);
+    assert!(timeline.iter().all(|entry| entry.decrypted == Some(true)));
+    let timeline = decode_timeline(&encoded, 32_000, &decode);
+    assert!(timeline.iter().all(|entry| entry.message.is_empty() && entry.decrypted == Some(false)));
+}
+
+#[test]
+fn entries_too_large_for_a_frame_are_rejected() {
+    let mut engine = Engine::new();
+    let encode = EncodeOptions {
+        mode: EmbeddingMode::Qim,
+        encryption_key: Some(b"secret".to_vec()),
+        ..EncodeOptions::default()
+    };
+    let audio = generate(Signal::SpeechLike, 16_000, 2.0);
+    let sealed = encode_timeline(&audio, 16_000, &[(0.0, "sealed".to_string())], &encode);
+    assert!(sealed.is_err_and(|err| err.contains("a frame carries")));
+
+    // So are timelines with nothing to embed, or blocks with no length
+    assert!(engine.encode_timeline(&audio, 16_000, &[], &encode).is_err());
+    for block_seconds in [0.0, -1.0, f32::NAN] {
+        let blocks = engine.encode_timeline_with(&audio, 16_000, block_seconds, |_| "block".to_string(), &encode);
+        assert!(blocks.is_err(), "{block_seconds} s blocks");
+    }
+}